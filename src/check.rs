@@ -0,0 +1,98 @@
+//! Validation of a runt configuration without running any tests.
+use crate::{
+    errors::RuntError,
+    executor::{self, suite},
+    picker::{toml::Config, Picker},
+};
+use colored::*;
+use std::{collections::HashSet, convert::TryFrom, path::Path};
+
+/// Check the `runt.toml` in `conf_dir` and print out each suite with the
+/// number of tests it contains along with problems in the configuration:
+///   - Duplicate suite names.
+///   - `expect_dir`s that do not exist.
+///   - Tests that share an expect file.
+///   - Dependencies on unknown suites and cyclic dependencies.
+///   - Suites whose tests cannot be gathered, for example, because of an
+///     invalid glob pattern.
+///
+/// Returns the number of problems found.
+pub fn check(conf_dir: &Path) -> Result<i32, RuntError> {
+    let config = Config::from_path(conf_dir)?;
+    let mut problems: Vec<String> = config
+        .unknown_dependencies()
+        .into_iter()
+        .map(|(name, dep)| {
+            format!("{}: depends_on names unknown suite `{}`.", name, dep)
+        })
+        .collect();
+    let Config {
+        tests, directives, ..
+    } = config;
+    std::env::set_current_dir(conf_dir)?;
+
+    // Name, expect_dir, and generated suites for each suite configuration.
+    let mut groups = Vec::with_capacity(tests.len() + directives.len());
+    for conf in tests {
+        let (name, expect_dir) = (conf.name.clone(), conf.expect_dir.clone());
+        match suite::Suite::try_from(conf) {
+            Ok(suite) => groups.push((name, expect_dir, vec![suite])),
+            Err(RuntError(msg)) => problems.push(msg),
+        }
+    }
+    for conf in directives {
+        match conf.suites() {
+            Ok(suites) => groups.push((conf.name, conf.expect_dir, suites)),
+            Err(RuntError(msg)) => problems.push(msg),
+        }
+    }
+
+    let mut names = HashSet::new();
+    let mut suites = Vec::new();
+    for (name, expect_dir, group) in groups {
+        if !names.insert(name.clone()) {
+            problems.push(format!("Duplicate suite name `{}`.", name));
+        }
+        if let Some(dir) = expect_dir.filter(|dir| !dir.is_dir()) {
+            problems.push(format!(
+                "{}: expect_dir {} does not exist.",
+                name,
+                dir.to_string_lossy()
+            ));
+        }
+        let count: usize = group.iter().map(|suite| suite.paths.len()).sum();
+        println!(
+            "{} ({} {})",
+            name.bold(),
+            count,
+            if count == 1 { "test" } else { "tests" }
+        );
+        suites.extend(group);
+    }
+
+    match executor::Context::from(suites, 1) {
+        Ok(ctx) => problems.extend(ctx.expect_collisions().into_iter().map(
+            |(expect, tests)| {
+                format!(
+                    "Tests share the expect file {}: {}.",
+                    expect.to_string_lossy(),
+                    tests
+                        .iter()
+                        .map(|test| test.full_name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            },
+        )),
+        Err(RuntError(msg)) => problems.push(msg),
+    }
+
+    for problem in &problems {
+        println!("{}: {}", "error".red().bold(), problem);
+    }
+    if problems.is_empty() {
+        println!("{}", "Configuration is valid.".green().bold());
+    }
+
+    Ok(problems.len() as i32)
+}
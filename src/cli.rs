@@ -49,20 +49,81 @@ pub struct Opts {
     #[argh(option, short = 'i', long = "include")]
     pub include_filter: Option<String>,
 
-    /// limit the number of jobs to run in parallel. Defaults to number of logical
-    /// cpus.
+    /// only run test suites with this tag. Can be repeated to select suites
+    /// with any of the tags.
+    #[argh(option, long = "tag")]
+    pub tags: Vec<String>,
+
+    /// do not run test suites with this tag. Can be repeated.
+    #[argh(option, long = "skip-tag")]
+    pub skip_tags: Vec<String>,
+
+    /// re-run tests that mismatch or time out up to this many times.
+    /// Overrides the `retries` option of the test suites.
+    #[argh(option, long = "retries")]
+    pub retries: Option<u32>,
+
+    /// limit the number of jobs to run in parallel. Defaults to the `jobs`
+    /// option in [defaults] or the number of logical cpus.
     #[argh(option, short = 'j', long = "jobs")]
     pub jobs_limit: Option<usize>,
 
     /// maximum number of features that can be created for concurrent processing.
     /// Use a lower number if runt gives the "too many file handles" error.
-    /// Defaults to 50.
+    /// Defaults to the `max_futures` option in [defaults] or 50.
     #[argh(option, long = "max-futures")]
     pub max_futures: Option<usize>,
 
     /// print the version of runt
     #[argh(switch, short = 'V')]
     pub version: bool,
+
+    /// alternative modes of operation.
+    #[argh(subcommand)]
+    pub mode: Option<Mode>,
+}
+
+/// Alternative modes of operation.
+#[derive(FromArgs)]
+#[argh(subcommand)]
+pub enum Mode {
+    Check(CheckOpts),
+    Init(InitOpts),
+}
+
+#[derive(FromArgs)]
+/// Validate the runt.toml configuration without running any tests.
+#[argh(subcommand, name = "check")]
+pub struct CheckOpts {
+    /// test folder. Defaults to the test folder of runt.
+    #[argh(positional, from_str_fn(read_path))]
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(FromArgs)]
+/// Create a new runt.toml with a single test suite.
+#[argh(subcommand, name = "init")]
+pub struct InitOpts {
+    /// directory to create the runt.toml in. Defaults to the test folder of
+    /// runt.
+    #[argh(positional, from_str_fn(read_path))]
+    pub dir: Option<PathBuf>,
+
+    /// glob pattern for the input files of the tests. Can be repeated.
+    #[argh(option)]
+    pub paths: Vec<String>,
+
+    /// command to run for each input file. {} is replaced with its path.
+    #[argh(option)]
+    pub cmd: String,
+
+    /// name of the test suite. Defaults to "Tests".
+    #[argh(option, default = "String::from(\"Tests\")")]
+    pub name: String,
+
+    /// run the tests once and save their .expect files.
+    #[argh(switch, short = 's')]
+    pub save: bool,
 }
 
 fn read_path(path: &str) -> Result<PathBuf, String> {
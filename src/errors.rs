@@ -18,6 +18,12 @@ impl fmt::Display for RuntError {
 
 impl error::Error for RuntError {}
 
+/// Print a warning that does not stop Runt's execution.
+pub fn warn<S: fmt::Display>(msg: S) {
+    use colored::*;
+    eprintln!("{}: {}", "warning".yellow().bold(), msg);
+}
+
 impl From<string::FromUtf8Error> for RuntError {
     fn from(err: string::FromUtf8Error) -> Self {
         RuntError(err.to_string())
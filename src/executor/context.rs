@@ -1,30 +1,46 @@
-use super::{results, suite, Test};
+use super::{hooks::Hooks, process, results, suite, Test};
 use crate::{
     cli,
     errors::{self, RuntError},
 };
 use futures::{
+    future::{self, Either},
     io::{AllowStdIo, AsyncWriteExt},
     stream, StreamExt,
 };
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
 
 /// An executor manages the execution of a list of tests.
 pub struct Executor {
     /// Test configurations to be executed.
     tests: Vec<Test>,
+    /// Setup and teardown commands for each test suite.
+    hooks: Vec<Arc<Hooks>>,
     /// Maximum number of futures that can be created.
     max_futures: usize,
 }
 impl Executor {
+    /// The tests to be executed.
+    pub fn tests(&self) -> &[Test] {
+        &self.tests
+    }
+
     /// Execute the test suites and generate test results in any order.
+    /// The tests of a suite start after the suites it depends on finish. Since
+    /// the tests are ordered after their dependencies, waiting tests never
+    /// prevent their dependencies from running.
     /// It is the job of the consumer of this method to collect the results and
     /// display them in the desired manner (grouped by test suite or order of
     /// completion)
     pub fn execute_all(
         self,
     ) -> impl stream::Stream<Item = Result<results::Test, RuntError>> {
-        stream::iter(self.tests.into_iter().map(|test| test.execute_test()))
-            .buffer_unordered(self.max_futures)
+        let hooks = self.hooks;
+        stream::iter(self.tests.into_iter().map(move |test| {
+            let hooks = Arc::clone(&hooks[test.test_suite as usize]);
+            async move { hooks.execute_test(test).await }
+        }))
+        .buffer_unordered(self.max_futures)
     }
 }
 
@@ -32,9 +48,11 @@ impl Executor {
 pub struct Status {
     pub miss: u64,
     pub pass: u64,
+    pub flaky: u64,
     pub remain: u64,
     pub skip: u64,
     pub fail: u64,
+    pub xfail: u64,
     pub timeout: u64,
     /// Handle to the output
     handle: AllowStdIo<std::io::BufWriter<std::io::Stdout>>,
@@ -51,8 +69,10 @@ impl Status {
             remain: total,
             miss: 0,
             pass: 0,
+            flaky: 0,
             skip: 0,
             fail: 0,
+            xfail: 0,
             timeout: 0,
             handle,
             istty,
@@ -64,11 +84,15 @@ impl Status {
         use colored::*;
 
         format!(
-            " {} {} / {} {} / {} {} / {} {} / {} {}",
+            " {} {} / {} {} / {} {} / {} {} / {} {} / {} {} / {} {}",
             self.pass.to_string().green().bold(),
             &"passing".green().bold(),
+            self.flaky.to_string().yellow().bold(),
+            &"flaky".yellow().bold(),
             (self.fail + self.timeout).to_string().red().bold(),
             &"failing".red().bold(),
+            self.xfail.to_string().yellow().dimmed().bold(),
+            &"expected failing".yellow().dimmed().bold(),
             self.miss.to_string().yellow().bold(),
             &"missing".yellow().bold(),
             self.skip.to_string().yellow().dimmed().bold(),
@@ -124,9 +148,19 @@ impl Status {
             results::State::Correct => {
                 self.pass += 1;
             }
-            results::State::Mismatch(..) => {
+            results::State::Flaky(..) => {
+                self.flaky += 1;
+            }
+            results::State::Mismatch(..)
+            | results::State::SetupFailed(..)
+            | results::State::Blocked(..)
+            | results::State::UnexpectedPass
+            | results::State::LimitExceeded(..) => {
                 self.fail += 1;
             }
+            results::State::ExpectedFail(..) => {
+                self.xfail += 1;
+            }
             results::State::Timeout => {
                 self.timeout += 1;
             }
@@ -148,26 +182,122 @@ pub struct Context {
 }
 
 impl Context {
+    /// Groups of tests that share the same expect file.
+    pub fn expect_collisions(&self) -> Vec<(PathBuf, Vec<&Test>)> {
+        let mut expects: BTreeMap<PathBuf, Vec<&Test>> = BTreeMap::new();
+        for test in &self.exec.tests {
+            expects.entry(test.expect_file()).or_default().push(test);
+        }
+        expects
+            .into_iter()
+            .filter(|(_, tests)| tests.len() > 1)
+            .collect()
+    }
+
+    /// Order the suites so that each suite comes after the suites it depends
+    /// on. Returns the order of the suites along with the indices of the
+    /// dependencies of each suite. Dependencies that do not name one of the
+    /// suites are ignored.
+    fn order_suites(
+        suites: &[suite::Suite],
+    ) -> Result<(Vec<usize>, Vec<Vec<usize>>), RuntError> {
+        let deps: Vec<Vec<usize>> = suites
+            .iter()
+            .map(|suite| {
+                (0..suites.len())
+                    .filter(|&idx| {
+                        suite.config.depends_on.iter().any(|name| {
+                            suite::name_matches(&suites[idx].config.name, name)
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut order = Vec::with_capacity(suites.len());
+        let mut done = vec![false; suites.len()];
+        while order.len() < suites.len() {
+            let ready: Vec<usize> = (0..suites.len())
+                .filter(|&idx| {
+                    !done[idx] && deps[idx].iter().all(|&dep| done[dep])
+                })
+                .collect();
+            if ready.is_empty() {
+                let cycle: Vec<&str> = (0..suites.len())
+                    .filter(|&idx| !done[idx])
+                    .map(|idx| suites[idx].config.name.as_str())
+                    .collect();
+                return Err(RuntError(format!(
+                    "Suites have cyclic dependencies: {}.",
+                    cycle.join(", ")
+                )));
+            }
+            for idx in ready {
+                done[idx] = true;
+                order.push(idx);
+            }
+        }
+        Ok((order, deps))
+    }
+
     /// Construct a new [Context] using suites and a maximum number of futures
     /// allowed to run concurrently.
-    pub fn from(suites: Vec<suite::Suite>, max_futures: usize) -> Self {
+    /// The tests of a suite are ordered after the tests of the suites it
+    /// depends on. Returns an error if the dependencies of the suites form a
+    /// cycle.
+    pub fn from(
+        suites: Vec<suite::Suite>,
+        max_futures: usize,
+    ) -> Result<Self, RuntError> {
+        let (order, deps) = Self::order_suites(&suites)?;
+        // Position of each suite in the order.
+        let mut position = vec![0; suites.len()];
+        for (pos, &idx) in order.iter().enumerate() {
+            position[idx] = pos;
+        }
+
+        let mut suites: Vec<Option<suite::Suite>> =
+            suites.into_iter().map(Some).collect();
         let mut configs = Vec::with_capacity(suites.len());
         let mut tests = Vec::with_capacity(suites.len());
-        for (idx, suite) in suites.into_iter().enumerate() {
-            let suite::Suite { config, paths } = suite;
+        let mut hooks: Vec<Arc<Hooks>> = Vec::with_capacity(suites.len());
+        for (idx, &orig) in order.iter().enumerate() {
+            let suite::Suite { config, paths } =
+                suites[orig].take().expect("Suite ordered twice");
+            let dep_hooks = deps[orig]
+                .iter()
+                .map(|&dep| Arc::clone(&hooks[position[dep]]))
+                .collect();
+            hooks.push(Arc::new(Hooks::new(&config, paths.len(), dep_hooks)));
             tests.extend(paths.into_iter().map(|path| Test {
+                xfail: config.xfail.contains(&path),
                 path,
                 cmd: config.cmd.clone(),
+                shell: config.shell.clone(),
                 expect_dir: config.expect_dir.clone(),
+                expect_path: config.expect_path.clone(),
+                dir: config.dir.clone(),
                 test_suite: idx as u64,
+                suite_name: config.name.clone(),
                 timeout: config.timeout,
+                env: config.env.clone(),
+                cwd: config.cwd.clone(),
+                skip: config.skip,
+                stdin: config.stdin.clone(),
+                retries: config.retries,
+                grace_period: config.grace_period,
+                limits: config.limits.clone(),
             }));
             configs.push(config);
         }
-        Context {
-            exec: Executor { tests, max_futures },
+        Ok(Context {
+            exec: Executor {
+                tests,
+                hooks,
+                max_futures,
+            },
             configs,
-        }
+        })
     }
 
     /// Generates a streaming summary of the test results.
@@ -181,29 +311,57 @@ impl Context {
         // Initial summary printing to give user feedback that runt has started.
         st.stream_summary().await?;
 
-        while let Some(result) = tasks.next().await {
-            let mut res = result?;
+        let mut interrupt = Box::pin(tokio::signal::ctrl_c());
+        let mut interrupted = false;
+        let configs = &self.configs;
+        let run: Result<(), errors::RuntError> = async {
+            loop {
+                let result =
+                    match future::select(tasks.next(), &mut interrupt).await {
+                        Either::Left((Some(result), _)) => result,
+                        Either::Left((None, _)) => break,
+                        Either::Right(_) => {
+                            interrupted = true;
+                            return Err(RuntError("Interrupted.".to_string()));
+                        }
+                    };
+                let mut res = result?;
 
-            // Save the result if needed
-            if res.should_save(opts) {
-                res.save_results().await?;
-            }
+                // Save the result if needed
+                if res.should_save(opts) {
+                    res.save_results().await?;
+                }
 
-            // Update summary
-            st.update(&res.state);
+                // Update summary
+                st.update(&res.state);
 
-            // Clear the current line to print the updating counter.
-            st.clear().await?;
+                // Clear the current line to print the updating counter.
+                st.clear().await?;
 
-            // Print test information if needed.
-            if res.should_print(opts) {
-                let suite_name = &self.configs[res.test_suite as usize].name;
-                st.print(res.report_str(Some(suite_name), opts.diff))
-                    .await?;
+                // Print test information if needed.
+                if res.should_print(opts) {
+                    let suite_name = &configs[res.test_suite as usize].name;
+                    st.print(res.report_str(Some(suite_name), opts.diff))
+                        .await?;
+                }
+
+                // Print out the current summary
+                st.stream_summary().await?;
             }
+            Ok(())
+        }
+        .await;
 
-            // Print out the current summary
-            st.stream_summary().await?;
+        if let Err(err) = run {
+            // Terminate the running tests before they are dropped so that
+            // their processes do not outlive runt.
+            process::terminate_all().await;
+            if interrupted {
+                st.clear().await?;
+                st.print_summary().await?;
+                println!();
+            }
+            return Err(err);
         }
 
         // Print the final summary
@@ -211,11 +369,16 @@ impl Context {
         st.print_summary().await?;
         println!();
 
+        // `runt init --save` saves the expect files of its missing tests.
+        if matches!(opts.mode, Some(cli::Mode::Init(_))) {
+            return Ok((st.fail + st.timeout) as i32);
+        }
+
         match opts.post_filter {
             Some(cli::OnlyOpt::Fail) => Ok((st.fail + st.timeout) as i32),
             Some(cli::OnlyOpt::Missing) => Ok((st.miss) as i32),
             Some(cli::OnlyOpt::Pass) => Ok(0),
-            None => Ok((st.fail + st.timeout + st.miss) as i32)
+            None => Ok((st.fail + st.timeout + st.miss) as i32),
         }
     }
 }
@@ -0,0 +1,169 @@
+//! Setup and teardown commands that run around the tests of a test suite and
+//! the dependencies between test suites.
+use super::{results, suite, Test};
+use crate::errors::{self, RuntError};
+use futures::{
+    channel::oneshot,
+    future::{self, BoxFuture, FutureExt, Shared},
+};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use tokio::process::Command;
+
+/// Tracks the setup and teardown commands of a test suite while its tests
+/// execute.
+pub struct Hooks {
+    /// Name of the test suite.
+    name: String,
+    /// The setup command. Runs once when the first test of the suite starts
+    /// and resolves to the output of the command if it failed.
+    setup: Shared<BoxFuture<'static, Result<(), String>>>,
+    /// The teardown command. Runs after the last test of the suite finishes.
+    teardown: Mutex<Option<Command>>,
+    /// Number of tests in the suite that have not finished.
+    remaining: AtomicUsize,
+    /// Hooks of the suites that must finish before the tests of this suite
+    /// start.
+    deps: Vec<Arc<Hooks>>,
+    /// Set when a test of the suite fails.
+    failed: AtomicBool,
+    /// Resolves once all the tests of the suite have finished. The result is
+    /// `true` if none of them failed.
+    finished: Shared<BoxFuture<'static, bool>>,
+    /// Resolves `finished` when the last test of the suite finishes.
+    finish: Mutex<Option<oneshot::Sender<bool>>>,
+}
+
+impl Hooks {
+    /// Construct the hooks for a suite with `tests` number of tests that
+    /// depends on the suites with the hooks `deps`.
+    pub fn new(
+        config: &suite::Config,
+        tests: usize,
+        deps: Vec<Arc<Hooks>>,
+    ) -> Self {
+        let teardown = config
+            .teardown
+            .as_ref()
+            .map(|cmd| hook_command(config, cmd))
+            .transpose();
+        let setup = match (&config.setup, &teardown) {
+            // Hooks cannot run without a shell so the tests are reported as
+            // failing setup.
+            (_, Err(err)) => future::ready(Err(err.clone())).boxed(),
+            (Some(cmd), _) => match hook_command(config, cmd) {
+                Ok(cmd) => run_hook(cmd).boxed(),
+                Err(err) => future::ready(Err(err)).boxed(),
+            },
+            (None, _) => future::ready(Ok(())).boxed(),
+        };
+        let (finish, done) = oneshot::channel();
+        // A suite without tests finishes when its dependencies finish.
+        let finished = if tests == 0 {
+            future::join_all(deps.iter().map(|dep| dep.finished.clone()))
+                .map(|passed| passed.into_iter().all(|passed| passed))
+                .boxed()
+        } else {
+            done.map(|passed| passed.unwrap_or(false)).boxed()
+        };
+        Hooks {
+            name: config.name.clone(),
+            setup: setup.shared(),
+            teardown: Mutex::new(teardown.ok().flatten()),
+            remaining: AtomicUsize::new(tests),
+            deps,
+            failed: AtomicBool::new(false),
+            finished: finished.shared(),
+            finish: Mutex::new(Some(finish)),
+        }
+    }
+
+    /// Names of the dependencies of the suite that failed. Waits for all the
+    /// dependencies to finish.
+    async fn failed_deps(&self) -> Vec<String> {
+        let mut failed: Vec<String> = Vec::new();
+        for dep in &self.deps {
+            if !dep.finished.clone().await && !failed.contains(&dep.name) {
+                failed.push(dep.name.clone());
+            }
+        }
+        failed
+    }
+
+    /// Execute `test` once the dependencies of the suite and its setup command
+    /// have finished. If a dependency or the setup command failed, the test
+    /// is not run.
+    /// The last test of the suite runs the teardown command after it finishes.
+    pub async fn execute_test(
+        &self,
+        test: Test,
+    ) -> Result<results::Test, RuntError> {
+        let failed_deps = self.failed_deps().await;
+        let res = if !failed_deps.is_empty() {
+            Ok(test.blocked(failed_deps))
+        } else {
+            match self.setup.clone().await {
+                Ok(()) => test.execute_test().await,
+                Err(output) => Ok(test.setup_failed(output)),
+            }
+        };
+
+        if res.as_ref().map_or(true, results::Test::failed) {
+            self.failed.store(true, Ordering::SeqCst);
+        }
+
+        if self.remaining.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // The setup command does not run if all the tests are blocked.
+            if let Some(Ok(())) = self.setup.peek() {
+                let teardown = self.teardown.lock().unwrap().take();
+                if let Some(cmd) = teardown {
+                    if let Err(output) = run_hook(cmd).await {
+                        errors::warn(format!(
+                            "Teardown for {} failed:\n{}",
+                            self.name, output
+                        ));
+                    }
+                }
+            }
+            if let Some(finish) = self.finish.lock().unwrap().take() {
+                // The receiver is dropped if no suite depends on this one.
+                let _ = finish.send(!self.failed.load(Ordering::SeqCst));
+            }
+        }
+
+        res
+    }
+}
+
+/// Construct a command that runs `cmd` using the shell and environment of the
+/// suite. Hooks run in the directory of the configuration that defines the
+/// suite regardless of its `cwd`. Fails if the shell of the suite is empty.
+fn hook_command(config: &suite::Config, cmd: &str) -> Result<Command, String> {
+    let (shell, args) = config
+        .shell
+        .split_first()
+        .ok_or_else(|| format!("{}: Empty shell.", config.name))?;
+    let mut command = Command::new(shell);
+    command.args(args).arg(cmd);
+    if !config.dir.as_os_str().is_empty() {
+        command.current_dir(&config.dir);
+    }
+    command.envs(&config.env).env("RUNT_SUITE", &config.name);
+    command.kill_on_drop(true);
+    Ok(command)
+}
+
+/// Run a hook command. Returns the output of the command if it failed.
+async fn run_hook(mut cmd: Command) -> Result<(), String> {
+    match cmd.output().await {
+        Err(err) => Err(err.to_string()),
+        Ok(out) if out.status.success() => Ok(()),
+        Ok(out) => Err(Test::format_expect_string(
+            out.status.code().unwrap_or(-1),
+            &String::from_utf8_lossy(&out.stdout),
+            &String::from_utf8_lossy(&out.stderr),
+        )),
+    }
+}
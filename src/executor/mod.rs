@@ -1,8 +1,11 @@
 //! An executor is responsible for executing the test configurations and generating results.
 
 mod context;
+mod hooks;
+mod process;
 pub mod results;
 pub mod suite;
+mod template;
 mod test;
 
 pub use context::Context;
@@ -0,0 +1,226 @@
+//! Process groups and resource limits of running tests. Each test runs in
+//! its own process group so that the processes it spawns can be terminated
+//! along with it.
+use super::suite;
+use std::{
+    collections::BTreeMap,
+    io, mem,
+    os::unix::process::ExitStatusExt,
+    process::ExitStatus,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::{
+    process::{Child, Command},
+    time,
+};
+
+/// Interval between checks for the exit of a process group.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Process groups of the running tests with their grace periods.
+static GROUPS: Mutex<BTreeMap<i32, Duration>> = Mutex::new(BTreeMap::new());
+
+/// Send `signal` to the process group `pgid`.
+fn signal(pgid: i32, signal: libc::c_int) {
+    // SAFETY: killpg does not access memory and fails if the group does not
+    // exist.
+    unsafe {
+        libc::killpg(pgid, signal);
+    }
+}
+
+/// Check if any process in the group `pgid` exists. Exited children of runt
+/// exist until they are reaped.
+fn exists(pgid: i32) -> bool {
+    // SAFETY: killpg does not access memory. Signal 0 only checks for the
+    // existence of the group.
+    unsafe { libc::killpg(pgid, 0) == 0 }
+}
+
+/// Reap the exited children of runt in the group `pgid`.
+fn reap(pgid: i32) {
+    // SAFETY: waitpid with a null status pointer does not access memory.
+    while unsafe { libc::waitpid(-pgid, std::ptr::null_mut(), libc::WNOHANG) }
+        > 0
+    {}
+}
+
+/// The process group of a running test. The group is registered until this
+/// is dropped so that [terminate_all] can reach it.
+pub(super) struct Group {
+    /// Id of the process group which is the pid of its leader.
+    pgid: i32,
+    /// Time between SIGTERM and SIGKILL when the group is terminated.
+    grace: Duration,
+}
+
+impl Group {
+    /// Register the process group led by `child` which was spawned with
+    /// `process_group(0)`. Returns `None` if the child has already exited.
+    pub fn new(child: &Child, grace: Duration) -> Option<Self> {
+        let pgid = child.id()? as i32;
+        GROUPS.lock().unwrap().insert(pgid, grace);
+        Some(Group { pgid, grace })
+    }
+
+    /// Send SIGTERM to the group and SIGKILL if it has not exited after the
+    /// grace period. `leader` is the child that leads the group.
+    pub async fn terminate(&self, leader: &mut Child) {
+        signal(self.pgid, libc::SIGTERM);
+        let start = Instant::now();
+        loop {
+            // Reap the leader so that it does not keep the group alive.
+            let _ = leader.try_wait();
+            if !exists(self.pgid) {
+                return;
+            }
+            if start.elapsed() >= self.grace {
+                signal(self.pgid, libc::SIGKILL);
+                let _ = leader.wait().await;
+                return;
+            }
+            time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl Drop for Group {
+    fn drop(&mut self) {
+        GROUPS.lock().unwrap().remove(&self.pgid);
+    }
+}
+
+/// Terminate the process groups of all the running tests. Each group is sent
+/// SIGTERM and then SIGKILL if it has not exited after its grace period.
+pub(super) async fn terminate_all() {
+    let mut groups = GROUPS.lock().unwrap().clone();
+    for pgid in groups.keys() {
+        signal(*pgid, libc::SIGTERM);
+    }
+    let start = Instant::now();
+    while !groups.is_empty() {
+        groups.retain(|pgid, grace| {
+            reap(*pgid);
+            if !exists(*pgid) {
+                false
+            } else if start.elapsed() >= *grace {
+                signal(*pgid, libc::SIGKILL);
+                false
+            } else {
+                true
+            }
+        });
+        time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Set the resource `limits` of the processes spawned by `cmd`. The limits
+/// are inherited by the processes started by the command.
+pub(super) fn set_limits(cmd: &mut Command, limits: &suite::Limits) {
+    let mut rlimits = Vec::new();
+    if let Some(memory) = limits.memory {
+        rlimits.push((libc::RLIMIT_AS, memory, memory));
+    }
+    if let Some(cpu) = limits.cpu_seconds {
+        // The soft limit sends SIGXCPU and the hard limit SIGKILL.
+        rlimits.push((libc::RLIMIT_CPU, cpu, cpu + 1));
+    }
+    if let Some(size) = limits.file_size {
+        rlimits.push((libc::RLIMIT_FSIZE, size, size));
+    }
+    if rlimits.is_empty() {
+        return;
+    }
+    let set = move || {
+        for &(resource, soft, hard) in &rlimits {
+            let limit = libc::rlimit {
+                rlim_cur: soft as libc::rlim_t,
+                rlim_max: hard as libc::rlim_t,
+            };
+            // SAFETY: setrlimit only reads `limit` and is async-signal-safe.
+            if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    };
+    // SAFETY: The closure only calls setrlimit which is safe to call
+    // between fork and exec. It does not allocate.
+    unsafe {
+        cmd.pre_exec(set);
+    }
+}
+
+/// Wait for `child` to exit without reaping it and return the processor time
+/// used by it and its reaped children. The child is left to be reaped by
+/// [Child::wait]. Returns `None` if the child has already been reaped.
+#[cfg(target_os = "linux")]
+pub(super) async fn cpu_time(child: &Child) -> io::Result<Option<Duration>> {
+    let pid = match child.id() {
+        Some(pid) => pid,
+        None => return Ok(None),
+    };
+    let usage = tokio::task::spawn_blocking(move || loop {
+        // SAFETY: Zeroed siginfo_t and rusage are valid values.
+        let (mut info, mut usage): (libc::siginfo_t, libc::rusage) =
+            unsafe { (mem::zeroed(), mem::zeroed()) };
+        // SAFETY: waitid only writes to `info` and `usage`. The system call
+        // is used instead of the libc wrapper because only the system call
+        // reports the resource usage of the child.
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_waitid,
+                libc::P_PID,
+                pid,
+                &mut info as *mut libc::siginfo_t,
+                libc::WEXITED | libc::WNOWAIT,
+                &mut usage as *mut libc::rusage,
+            )
+        };
+        if ret == 0 {
+            return Ok(usage);
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    })
+    .await??;
+    let time = |tv: libc::timeval| {
+        Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000)
+    };
+    Ok(Some(time(usage.ru_utime) + time(usage.ru_stime)))
+}
+
+/// The processor time of a child is only available on Linux.
+#[cfg(not(target_os = "linux"))]
+pub(super) async fn cpu_time(_: &Child) -> io::Result<Option<Duration>> {
+    Ok(None)
+}
+
+/// The resource limit that caused the command to exit with `status` after
+/// using `cpu_time` of processor time, if any. Only limits that certainly
+/// caused the exit are reported: the processor time and file size limits send
+/// SIGXCPU and SIGXFSZ, and a command that ignores SIGXCPU is sent SIGKILL
+/// once its processor time exceeds the limit. Allocations that exceed the
+/// memory limit fail and are left to the command to report.
+pub(super) fn exceeded_limit(
+    limits: &suite::Limits,
+    status: ExitStatus,
+    cpu_time: Option<Duration>,
+) -> Option<suite::Limit> {
+    match status.signal()? {
+        libc::SIGXCPU if limits.cpu_seconds.is_some() => {
+            Some(suite::Limit::CpuTime)
+        }
+        libc::SIGKILL => {
+            let limit = Duration::from_secs(limits.cpu_seconds?);
+            (cpu_time? >= limit).then_some(suite::Limit::CpuTime)
+        }
+        libc::SIGXFSZ if limits.file_size.is_some() => {
+            Some(suite::Limit::FileSize)
+        }
+        _ => None,
+    }
+}
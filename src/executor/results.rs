@@ -10,12 +10,28 @@ use super::suite;
 pub enum State {
     /// The test timed out.
     Timeout,
+    /// The test was killed because it exceeded a resource limit.
+    LimitExceeded(suite::Limit),
     /// The comparison succeeded.
     Correct,
+    /// The comparison succeeded after the test was re-run. Contains the
+    /// number of attempts.
+    Flaky(u32),
     /// The test was skipped because of a .skip file
     Skip,
+    /// The test was not run because the setup command of its suite failed.
+    /// Contains the output of the setup command.
+    SetupFailed(String),
+    /// The test was not run because a suite it depends on failed. Contains
+    /// the names of the failing suites.
+    Blocked(Vec<String>),
     /// The .expect file is missing. Contains the generated expectation string.
     Missing(String),
+    /// The comparison of a test that is expected to fail failed. Contains the
+    /// generated expectation string and the contents of the expect file.
+    ExpectedFail(String, String),
+    /// The comparison of a test that is expected to fail succeeded.
+    UnexpectedPass,
     /// The comparison failed. Contains the the generated expectation string
     /// and the contents of the expect file.
     Mismatch(
@@ -40,16 +56,41 @@ pub struct Test {
 }
 
 impl Test {
+    /// Check if the test failed. Tests with missing expect files are not
+    /// failing.
+    pub fn failed(&self) -> bool {
+        matches!(
+            self.state,
+            State::Timeout
+                | State::SetupFailed(..)
+                | State::Blocked(..)
+                | State::UnexpectedPass
+                | State::LimitExceeded(..)
+                | State::Mismatch(..)
+        )
+    }
+
     /// Save the results of the test suite into the expect file.
     pub async fn save_results(&mut self) -> Result<(), RuntError> {
         match &self.state {
-            State::Skip | State::Correct | State::Timeout => Ok(()),
+            State::Skip
+            | State::Correct
+            | State::Flaky(..)
+            | State::Timeout
+            | State::LimitExceeded(..)
+            | State::SetupFailed(..)
+            | State::Blocked(..)
+            | State::ExpectedFail(..)
+            | State::UnexpectedPass => Ok(()),
             State::Missing(expect) | State::Mismatch(expect, _) => {
                 self.saved = true;
+                if let Some(dir) = self.expect_path.parent() {
+                    fs::create_dir_all(dir).await?;
+                }
                 fs::write(&self.expect_path, expect).await.map_err(|err| {
                     RuntError(format!(
                         "{}: {}.",
-                        self.expect_path.to_str().unwrap(),
+                        self.expect_path.to_string_lossy(),
                         err
                     ))
                 })
@@ -64,7 +105,12 @@ impl Test {
         match (only, &self.state) {
             (O::Fail, State::Mismatch(..)) => true,
             (O::Fail, State::Timeout) => true,
+            (O::Fail, State::LimitExceeded(..)) => true,
+            (O::Fail, State::SetupFailed(..)) => true,
+            (O::Fail, State::Blocked(..)) => true,
+            (O::Fail, State::UnexpectedPass) => true,
             (O::Pass, State::Correct) => true,
+            (O::Pass, State::Flaky(..)) => true,
             (O::Missing, State::Missing(..)) => true,
             (O::Fail, _) | (O::Pass, _) | (O::Missing, _) => false,
         }
@@ -107,7 +153,7 @@ impl Test {
         use colored::*;
 
         let mut buf = String::new();
-        let path_str = self.path.to_str().unwrap();
+        let path_str = self.path.to_string_lossy();
         match &self.state {
             State::Skip => {
                 assert!(!self.saved, "Skipped files cannot be saved");
@@ -143,6 +189,39 @@ impl Test {
                 buf.push_str(&path_str.red());
                 buf.push_str(&" (timeout)".dimmed());
             }
+            State::LimitExceeded(limit) => {
+                buf.push_str(&"✗ ".red());
+                suite.into_iter().for_each(|suite_name| {
+                    buf.push_str(&suite_name.bold().red());
+                    buf.push_str(&":".red())
+                });
+                buf.push_str(&path_str.red());
+                buf.push_str(&format!(" ({} limit exceeded)", limit).dimmed());
+            }
+            State::SetupFailed(output) => {
+                buf.push_str(&"✗ ".red());
+                suite.into_iter().for_each(|suite_name| {
+                    buf.push_str(&suite_name.bold().red());
+                    buf.push_str(&":".red())
+                });
+                buf.push_str(&path_str.red());
+                buf.push_str(&" (setup failed)".dimmed());
+                if show_diff {
+                    buf.push('\n');
+                    buf.push_str(output.trim_end());
+                }
+            }
+            State::Blocked(deps) => {
+                buf.push_str(&"✗ ".red());
+                suite.into_iter().for_each(|suite_name| {
+                    buf.push_str(&suite_name.bold().red());
+                    buf.push_str(&":".red())
+                });
+                buf.push_str(&path_str.red());
+                buf.push_str(
+                    &format!(" (blocked by {})", deps.join(", ")).dimmed(),
+                );
+            }
             State::Correct => {
                 buf.push_str(&"✓ ".green());
                 suite.into_iter().for_each(|suite_name| {
@@ -151,6 +230,41 @@ impl Test {
                 });
                 buf.push_str(&path_str.green());
             }
+            State::Flaky(attempts) => {
+                buf.push_str(&"✓ ".yellow());
+                suite.into_iter().for_each(|suite_name| {
+                    buf.push_str(&suite_name.bold().yellow());
+                    buf.push_str(&":".yellow())
+                });
+                buf.push_str(&path_str.yellow());
+                buf.push_str(
+                    &format!(" (flaky, passed on attempt {})", attempts)
+                        .dimmed(),
+                );
+            }
+            State::ExpectedFail(expect_string, contents) => {
+                buf.push_str(&"✗ ".yellow().dimmed());
+                suite.into_iter().for_each(|suite_name| {
+                    buf.push_str(&suite_name.bold().yellow().dimmed());
+                    buf.push_str(&":".yellow().dimmed())
+                });
+                buf.push_str(&path_str.yellow().dimmed());
+                buf.push_str(&" (expected failure)".dimmed());
+                if show_diff {
+                    let diff = printer::gen_diff(contents, expect_string);
+                    buf.push('\n');
+                    buf.push_str(&diff);
+                }
+            }
+            State::UnexpectedPass => {
+                buf.push_str(&"✗ ".red());
+                suite.into_iter().for_each(|suite_name| {
+                    buf.push_str(&suite_name.bold().red());
+                    buf.push_str(&":".red())
+                });
+                buf.push_str(&path_str.red());
+                buf.push_str(&" (unexpectedly passing)".dimmed());
+            }
             State::Mismatch(expect_string, contents) => {
                 buf.push_str(&"✗ ".red());
                 suite.into_iter().for_each(|suite_name| {
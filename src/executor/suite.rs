@@ -1,23 +1,197 @@
 //! A Runt test suite configuration.
-use std::{path::PathBuf, time::Duration};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 
 use regex::Regex;
+use serde::Deserialize;
 
 /// Type for mapping test suite objects.
 pub type Id = u64;
 
+/// Working directory for the commands of a test suite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cwd {
+    /// The directory containing the runt configuration.
+    Root,
+    /// The directory containing the input file of each test.
+    InputDir,
+    /// A fixed directory.
+    Dir(PathBuf),
+}
+
+/// Command executed for each test of a suite.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Cmd {
+    /// A command string run by the shell. Placeholders are quoted.
+    Shell(String),
+    /// A program and its arguments executed without a shell. Placeholders
+    /// are substituted into each argument without quoting.
+    Argv(Vec<String>),
+}
+
+/// A resource limit of the commands of a test suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Processor time in seconds.
+    CpuTime,
+    /// Size of the files written by the command in bytes.
+    FileSize,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::CpuTime => write!(f, "cpu_seconds"),
+            Limit::FileSize => write!(f, "file_size"),
+        }
+    }
+}
+
+/// Resource limits applied to each process of a test command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum size of the virtual memory of each process in bytes.
+    pub memory: Option<u64>,
+    /// Maximum processor time of each process in seconds.
+    pub cpu_seconds: Option<u64>,
+    /// Maximum size of a file written by each process in bytes.
+    pub file_size: Option<u64>,
+}
+
+/// Standard input of the commands of a test suite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stdin {
+    /// No input.
+    Null,
+    /// Contents of the input file of each test.
+    File,
+    /// A fixed string.
+    Text(String),
+}
+
 /// Configuration for a test suite.
 pub struct Config {
     /// Name of this TestSuite
     pub name: String,
     /// Command to execute. The pattern `{}` in this string is replaced with
     /// the matching path.
-    pub cmd: String,
+    pub cmd: Cmd,
+    /// Shell and its arguments used to run shell commands and hooks.
+    /// Defaults to `sh -c`.
+    pub shell: Vec<String>,
     /// Optional directory to store the generated .expect files.
     pub expect_dir: Option<PathBuf>,
+    /// Optional template for the path of the .expect files.
+    /// Overrides `expect_dir`.
+    pub expect_path: Option<String>,
+    /// Directory of the configuration that defines this suite. The paths
+    /// generated by `expect_path` are relative to it.
+    pub dir: PathBuf,
     /// Optional timeout for the tests specified in seconds.
     /// Defaults to 1200 seconds.
     pub timeout: Duration,
+    /// Environment variables set for each test command.
+    pub env: HashMap<String, String>,
+    /// Working directory for each test command.
+    pub cwd: Cwd,
+    /// Optional command to run before the first test of this suite.
+    pub setup: Option<String>,
+    /// Optional command to run after the last test of this suite.
+    pub teardown: Option<String>,
+    /// Skip all the tests in this suite.
+    pub skip: bool,
+    /// Standard input for each test command.
+    pub stdin: Stdin,
+    /// Number of times a test that mismatches or times out is re-run.
+    pub retries: u32,
+    /// Paths of the tests that are expected to fail.
+    pub xfail: Vec<PathBuf>,
+    /// Time between SIGTERM and SIGKILL when a test is terminated because it
+    /// timed out or runt was interrupted.
+    pub grace_period: Duration,
+    /// Resource limits of each test command.
+    pub limits: Limits,
+    /// Tags used to select test suites from the command line.
+    pub tags: Vec<String>,
+    /// Names of the test suites that must finish before the tests of this
+    /// suite start.
+    pub depends_on: Vec<String>,
+}
+
+impl Config {
+    /// Construct a configuration that runs the shell command `cmd` for each
+    /// test with the default options.
+    pub fn new(name: String, cmd: String) -> Self {
+        Config {
+            name,
+            cmd: Cmd::Shell(cmd),
+            shell: vec!["sh".to_string(), "-c".to_string()],
+            expect_dir: None,
+            expect_path: None,
+            dir: PathBuf::new(),
+            timeout: Duration::from_secs(1200),
+            env: HashMap::new(),
+            cwd: Cwd::Root,
+            setup: None,
+            teardown: None,
+            skip: false,
+            stdin: Stdin::Null,
+            retries: 0,
+            xfail: Vec::new(),
+            grace_period: Duration::from_secs(5),
+            limits: Limits::default(),
+            tags: Vec::new(),
+            depends_on: Vec::new(),
+        }
+    }
+}
+
+/// Names of the placeholders that runt substitutes in commands and
+/// `expect_path` templates.
+pub const PLACEHOLDERS: &[&str] = &[
+    "", "path", "stem", "name", "ext", "dir", "suite", "expect", "args",
+    "relpath",
+];
+
+/// Check if `name` refers to the suite named `suite`. A name also refers to
+/// all the suites generated from a matrix, for example, `Build` refers to
+/// `Build[opt=-O2]`.
+pub fn name_matches(suite: &str, name: &str) -> bool {
+    suite == name
+        || suite
+            .strip_prefix(name)
+            .is_some_and(|rest| rest.starts_with('[') && rest.ends_with(']'))
+}
+
+/// Select the suites that match `tags` and `skip_tags` using
+/// [Suite::matches_tags] along with the suites that they depend on, directly
+/// or transitively, even if those do not match the tags.
+pub fn select_by_tags(
+    suites: Vec<Suite>,
+    tags: &[String],
+    skip_tags: &[String],
+) -> Vec<Suite> {
+    let mut selected: Vec<bool> = suites
+        .iter()
+        .map(|suite| suite.matches_tags(tags, skip_tags))
+        .collect();
+    let mut stack: Vec<usize> =
+        (0..suites.len()).filter(|&idx| selected[idx]).collect();
+    while let Some(idx) = stack.pop() {
+        for dep in &suites[idx].config.depends_on {
+            for (other, suite) in suites.iter().enumerate() {
+                if !selected[other] && name_matches(&suite.config.name, dep) {
+                    selected[other] = true;
+                    stack.push(other);
+                }
+            }
+        }
+    }
+    suites
+        .into_iter()
+        .zip(selected)
+        .filter_map(|(suite, selected)| selected.then_some(suite))
+        .collect()
 }
 
 /// Defines a test suite which is a collection of test paths, command, and other
@@ -46,4 +220,12 @@ impl Suite {
         });
         self
     }
+
+    /// Check if this test suite is selected by the tags. A suite is selected
+    /// if it has one of the tags in `tags`, or `tags` is empty, and it has
+    /// none of the tags in `skip_tags`.
+    pub fn matches_tags(&self, tags: &[String], skip_tags: &[String]) -> bool {
+        let has = |tag: &String| self.config.tags.contains(tag);
+        (tags.is_empty() || tags.iter().any(has)) && !skip_tags.iter().any(has)
+    }
 }
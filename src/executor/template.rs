@@ -0,0 +1,78 @@
+//! Placeholders of the form `{name}` that are substituted into test commands.
+use std::ffi::{OsStr, OsString};
+
+/// Characters that do not need to be quoted in a shell word.
+fn is_safe(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-_./,:=+@%^".contains(&b)
+}
+
+/// Quote `word` so that the shell interprets it as a single word. Words that
+/// only contain safe characters are returned unchanged.
+pub fn quote(word: &OsStr) -> OsString {
+    let bytes = word.as_encoded_bytes();
+    if !bytes.is_empty() && bytes.iter().copied().all(is_safe) {
+        return word.to_os_string();
+    }
+    let mut buf = OsString::with_capacity(bytes.len() + 2);
+    buf.push("'");
+    for (idx, part) in bytes.split(|b| *b == b'\'').enumerate() {
+        if idx > 0 {
+            buf.push(r"'\''");
+        }
+        // SAFETY: `part` is a substring of `word` split on an ASCII
+        // character which preserves the validity of the encoding.
+        buf.push(unsafe { OsStr::from_encoded_bytes_unchecked(part) });
+    }
+    buf.push("'");
+    buf
+}
+
+/// Render `template` by replacing each placeholder `{name}` with the value
+/// returned by `lookup`. Values are not quoted. Placeholders for which `lookup`
+/// returns `None` are left unchanged so that shell constructs like `${VAR}` are unaffected.
+/// A known placeholder can be escaped by doubling its braces: `{{}}` renders
+/// as `{}` and `{{stem}}` renders as `{stem}`.
+pub fn render<F>(template: &str, lookup: F) -> OsString
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    let mut buf = OsString::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        buf.push(&rest[..start]);
+        rest = &rest[start..];
+
+        // Escaped placeholder: `{{name}}`.
+        if let Some(inner) = rest
+            .strip_prefix("{{")
+            .and_then(|tail| tail.find("}}").map(|end| &tail[..end]))
+            .filter(|inner| !inner.contains(['{', '}']))
+            .filter(|inner| lookup(inner).is_some())
+        {
+            buf.push("{");
+            buf.push(inner);
+            buf.push("}");
+            rest = &rest[inner.len() + 4..];
+            continue;
+        }
+
+        // Placeholder: `{name}`.
+        let value = rest[1..]
+            .find('}')
+            .map(|end| &rest[1..end + 1])
+            .filter(|inner| !inner.contains('{'))
+            .and_then(|inner| lookup(inner).map(|value| (inner, value)));
+        match value {
+            Some((inner, value)) => {
+                buf.push(value);
+                rest = &rest[inner.len() + 2..];
+            }
+            None => {
+                buf.push("{");
+                rest = &rest[1..];
+            }
+        }
+    }
+    buf.push(rest);
+    buf
+}
@@ -1,25 +1,76 @@
-use super::{results, suite};
+use super::{process, results, suite, template};
 use crate::errors::RuntError;
-use std::{fs, path::PathBuf, time::Duration};
-use tokio::{process::Command, time};
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Output, Stdio},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    process::Command,
+    time,
+};
+
+/// Read the contents of the output `pipe` of a command, if any.
+async fn read_pipe<R: AsyncRead + Unpin>(
+    pipe: Option<R>,
+) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    if let Some(mut pipe) = pipe {
+        pipe.read_to_end(&mut buf).await?;
+    }
+    Ok(buf)
+}
 
 /// Configuration of a test to be executed.
 pub struct Test {
     /// Path of the test to be run.
     pub path: PathBuf,
     /// Command to be executed for the test.
-    pub cmd: String,
+    pub cmd: suite::Cmd,
+    /// Shell and its arguments used to run shell commands.
+    pub shell: Vec<String>,
     /// Directory to save/check the expect results for.
     /// If set to `None`, defaults to the directory containing `Path`.
     pub expect_dir: Option<PathBuf>,
+    /// Template for the path of the expect file. Overrides `expect_dir`.
+    pub expect_path: Option<String>,
+    /// Directory of the configuration that defines the suite of this test.
+    pub dir: PathBuf,
     /// Test suite with which this Test is associated.
     /// The mapping from the test suite
     pub test_suite: suite::Id,
+    /// Name of the test suite with which this Test is associated.
+    pub suite_name: String,
     /// Timeout for this test.
     pub timeout: Duration,
+    /// Environment variables set for the command.
+    pub env: HashMap<String, String>,
+    /// Working directory for the command.
+    pub cwd: suite::Cwd,
+    /// Skip this test without running it.
+    pub skip: bool,
+    /// Standard input of the command.
+    pub stdin: suite::Stdin,
+    /// Number of times the test is re-run if it mismatches or times out.
+    pub retries: u32,
+    /// The test is expected to fail.
+    pub xfail: bool,
+    /// Time between SIGTERM and SIGKILL when the test is terminated.
+    pub grace_period: Duration,
+    /// Resource limits of the command.
+    pub limits: suite::Limits,
 }
 
 impl Test {
+    /// Name of the test of the form `<suite-name>:<path>`.
+    pub fn full_name(&self) -> String {
+        format!("{}:{}", self.suite_name, self.path.to_string_lossy())
+    }
+
     /// Format the output of the test into an expect string.
     /// An expect string is of the form:
     /// <contents of STDOUT>
@@ -51,13 +102,38 @@ impl Test {
         buf
     }
 
+    /// Path of the expect file without its extension. The path of sidecar
+    /// files is computed by changing the extension.
+    /// When an `expect_path` template is specified, its placeholders are
+    /// replaced with the unquoted values:
+    /// - `{suite}`: Name of the test suite.
+    /// - `{relpath}`: Path of the test without its extension.
+    /// - `{path}`, `{stem}`, `{name}`, `{ext}`, `{dir}`: Same as the
+    ///   placeholders for the command.
     fn get_base(&self) -> PathBuf {
-        self.expect_dir
-            .clone()
-            .map(|base| base.join(self.path.file_name().unwrap()))
-            .unwrap_or_else(|| self.path.clone())
-            .as_path()
-            .to_path_buf()
+        if let Some(expect_path) = &self.expect_path {
+            let part = |part: Option<&OsStr>| {
+                Some(part.unwrap_or_default().to_os_string())
+            };
+            // The template and the paths of the tests are relative to the
+            // configuration that defines the suite.
+            let path = self.path.strip_prefix(&self.dir).unwrap_or(&self.path);
+            let rendered = template::render(expect_path, |name| match name {
+                "suite" => part(Some(OsStr::new(&self.suite_name))),
+                "relpath" => part(Some(path.with_extension("").as_os_str())),
+                "path" => part(Some(path.as_os_str())),
+                "stem" => part(path.file_stem()),
+                "name" => part(path.file_name()),
+                "ext" => part(path.extension()),
+                "dir" => part(path.parent().map(Path::as_os_str)),
+                _ => None,
+            });
+            return self.dir.join(rendered);
+        }
+        match (&self.expect_dir, self.path.file_name()) {
+            (Some(base), Some(name)) => base.join(name),
+            _ => self.path.clone(),
+        }
     }
 
     /// Path of the expect file.
@@ -70,15 +146,329 @@ impl Test {
         self.get_base().with_extension("skip")
     }
 
-    /// Construct a command to run by replacing all occurances of `{}` with that
-    /// matching path.
-    fn construct_command(&self) -> Command {
-        let concrete_command =
-            self.cmd.replace("{}", self.path.to_str().unwrap());
-        let mut cmd = Command::new("sh");
-        cmd.arg("-c").arg(concrete_command);
+    /// Path of the file marking the test as expected to fail.
+    pub fn xfail_file(&self) -> PathBuf {
+        self.get_base().with_extension("xfail")
+    }
+
+    /// Path of the file with extra arguments substituted for `{args}`.
+    pub fn args_file(&self) -> PathBuf {
+        self.get_base().with_extension("args")
+    }
+
+    /// Path of the file overriding the timeout of this test in seconds.
+    pub fn timeout_file(&self) -> PathBuf {
+        self.get_base().with_extension("timeout")
+    }
+
+    /// Path of the file with extra `KEY=VALUE` environment variables.
+    pub fn env_file(&self) -> PathBuf {
+        self.get_base().with_extension("env")
+    }
+
+    /// Read the contents of a sidecar file. Returns `None` if the file does
+    /// not exist.
+    fn read_sidecar(path: &Path) -> Result<Option<String>, RuntError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => {
+                Err(RuntError(format!("{}: {}", path.to_string_lossy(), err)))
+            }
+        }
+    }
+
+    /// Timeout for this test. The `.timeout` file overrides the timeout of the
+    /// suite.
+    fn get_timeout(&self) -> Result<Duration, RuntError> {
+        let path = self.timeout_file();
+        match Self::read_sidecar(&path)? {
+            None => Ok(self.timeout),
+            Some(secs) => {
+                secs.trim().parse().map(Duration::from_secs).map_err(|_| {
+                    RuntError(format!(
+                        "{}: Expected timeout in seconds, found `{}`.",
+                        path.to_string_lossy(),
+                        secs.trim()
+                    ))
+                })
+            }
+        }
+    }
+
+    /// Environment variables specified in the `.env` file. Each line has the
+    /// form `KEY=VALUE`. Empty lines and lines starting with `#` are ignored.
+    fn get_env(&self) -> Result<Vec<(String, String)>, RuntError> {
+        let path = self.env_file();
+        let contents = match Self::read_sidecar(&path)? {
+            None => return Ok(vec![]),
+            Some(contents) => contents,
+        };
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                line.split_once('=')
+                    .map(|(key, value)| {
+                        (key.trim().to_string(), value.trim().to_string())
+                    })
+                    .ok_or_else(|| {
+                        RuntError(format!(
+                            "{}: Expected KEY=VALUE, found `{}`.",
+                            path.to_string_lossy(),
+                            line
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// Directory to run the command in. `None` if the command runs in the
+    /// directory containing the runt configuration.
+    fn working_dir(&self) -> Option<&Path> {
+        match &self.cwd {
+            suite::Cwd::Root => None,
+            suite::Cwd::InputDir => {
+                self.path.parent().filter(|dir| !dir.as_os_str().is_empty())
+            }
+            suite::Cwd::Dir(dir) => Some(dir),
+        }
+    }
+
+    /// Rewrite `path` so that it remains valid from the working directory of
+    /// the command.
+    fn rebase(&self, path: &Path) -> io::Result<PathBuf> {
+        match self.working_dir() {
+            None => Ok(path.to_path_buf()),
+            Some(_) => std::path::absolute(path),
+        }
+    }
+
+    /// Path of the test as seen from the working directory of the command.
+    fn command_path(&self) -> io::Result<PathBuf> {
+        match (&self.cwd, self.path.file_name()) {
+            (suite::Cwd::InputDir, Some(name)) => Ok(name.into()),
+            _ => self.rebase(&self.path),
+        }
+    }
+
+    /// Render `template` by replacing its placeholders. Values are quoted for
+    /// the shell if `quote` is set.
+    /// - `{}` or `{path}`: Path of the test.
+    /// - `{stem}`: File name of the test without its extension.
+    /// - `{name}`: File name of the test.
+    /// - `{ext}`: Extension of the test.
+    /// - `{dir}`: Directory containing the test.
+    /// - `{suite}`: Name of the test suite.
+    /// - `{expect}`: Path of the expect file.
+    /// - `{args}`: Contents of the `.args` file which are never quoted. Empty
+    ///   if the file does not exist.
+    fn render(
+        &self,
+        template: &str,
+        quote: bool,
+    ) -> Result<OsString, RuntError> {
+        let path = self.command_path()?;
+        let expect = self.rebase(&self.expect_file())?;
+        let args = Self::read_sidecar(&self.args_file())?.unwrap_or_default();
+        let part = |part: Option<&OsStr>| {
+            let part = part.unwrap_or_default();
+            Some(if quote {
+                template::quote(part)
+            } else {
+                part.to_os_string()
+            })
+        };
+        Ok(template::render(template, |name| match name {
+            "" | "path" => part(Some(path.as_os_str())),
+            "stem" => part(self.path.file_stem()),
+            "name" => part(self.path.file_name()),
+            "ext" => part(self.path.extension()),
+            "dir" => part(Some(
+                path.parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or_else(|| Path::new("."))
+                    .as_os_str(),
+            )),
+            "suite" => part(Some(OsStr::new(&self.suite_name))),
+            "expect" => part(Some(expect.as_os_str())),
+            "args" => Some(args.trim().into()),
+            _ => None,
+        }))
+    }
+
+    /// Program and arguments to execute for this test.
+    /// A shell command is rendered with quoted placeholders and passed to the
+    /// shell. Each argument of an argv command is rendered without quoting and
+    /// an argument that is exactly `{args}` is split into words.
+    pub fn command_args(&self) -> Result<Vec<OsString>, RuntError> {
+        match &self.cmd {
+            suite::Cmd::Shell(cmd) => {
+                let mut argv: Vec<OsString> =
+                    self.shell.iter().map(OsString::from).collect();
+                argv.push(self.render(cmd, true)?);
+                Ok(argv)
+            }
+            suite::Cmd::Argv(argv) => {
+                let mut args = Vec::with_capacity(argv.len());
+                for arg in argv {
+                    if arg == "{args}" {
+                        let extra = self.render(arg, false)?;
+                        args.extend(
+                            extra
+                                .to_string_lossy()
+                                .split_whitespace()
+                                .map(OsString::from),
+                        );
+                    } else {
+                        args.push(self.render(arg, false)?);
+                    }
+                }
+                Ok(args)
+            }
+        }
+    }
+
+    /// Render the command for this test as it would be typed in a shell.
+    pub fn command_string(&self) -> Result<OsString, RuntError> {
+        match &self.cmd {
+            suite::Cmd::Shell(cmd) => self.render(cmd, true),
+            suite::Cmd::Argv(_) => {
+                let mut buf = OsString::new();
+                for (idx, arg) in self.command_args()?.iter().enumerate() {
+                    if idx > 0 {
+                        buf.push(" ");
+                    }
+                    buf.push(template::quote(arg));
+                }
+                Ok(buf)
+            }
+        }
+    }
+
+    /// Construct a command to run by replacing the placeholders in the
+    /// command.
+    /// The suite's environment variables are set on the command along with
+    /// `RUNT_SUITE`, `RUNT_TEST_PATH`, and `RUNT_EXPECT_PATH`. Variables in the
+    /// `.env` file override the ones of the suite.
+    fn construct_command(&self) -> Result<Command, RuntError> {
+        let argv = self.command_args()?;
+        let (program, args) = argv.split_first().ok_or_else(|| {
+            RuntError(format!("{}: Empty command.", self.full_name()))
+        })?;
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        if let Some(dir) = self.working_dir() {
+            cmd.current_dir(dir);
+        }
+        cmd.envs(&self.env)
+            .env("RUNT_SUITE", &self.suite_name)
+            .env("RUNT_TEST_PATH", self.command_path()?)
+            .env("RUNT_EXPECT_PATH", self.rebase(&self.expect_file())?)
+            .envs(self.get_env()?);
+        process::set_limits(&mut cmd, &self.limits);
         cmd.kill_on_drop(true);
-        cmd
+        Ok(cmd)
+    }
+
+    /// Run the command in its own process group and wait for its output
+    /// while feeding its standard input. If the command does not finish
+    /// within `timeout`, its process group is terminated and `None` is
+    /// returned. The processor time of the command is returned along with its
+    /// output when the suite limits it.
+    async fn run_command(
+        &self,
+        mut cmd: Command,
+        timeout: Duration,
+    ) -> io::Result<Option<(Output, Option<Duration>)>> {
+        let text = match &self.stdin {
+            suite::Stdin::Null => {
+                cmd.stdin(Stdio::null());
+                None
+            }
+            suite::Stdin::File => {
+                cmd.stdin(fs::File::open(&self.path)?);
+                None
+            }
+            suite::Stdin::Text(text) => {
+                cmd.stdin(Stdio::piped());
+                Some(text)
+            }
+        };
+        cmd.stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0);
+
+        let mut child = cmd.spawn()?;
+        let group = process::Group::new(&child, self.grace_period);
+        let stdin = child.stdin.take();
+        let write = async move {
+            if let (Some(mut stdin), Some(text)) = (stdin, text) {
+                // The command may exit without reading all of its input.
+                let _ = stdin.write_all(text.as_bytes()).await;
+            }
+            Ok(())
+        };
+        let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+        let cpu_seconds = self.limits.cpu_seconds;
+        let wait = async {
+            let cpu_time = match cpu_seconds {
+                Some(_) => process::cpu_time(&child).await?,
+                None => None,
+            };
+            Ok((child.wait().await?, cpu_time))
+        };
+        let output = async {
+            let ((), stdout, stderr, (status, cpu_time)) = futures::try_join!(
+                write,
+                read_pipe(stdout),
+                read_pipe(stderr),
+                wait
+            )?;
+            io::Result::Ok((
+                Output {
+                    status,
+                    stdout,
+                    stderr,
+                },
+                cpu_time,
+            ))
+        };
+
+        match time::timeout(timeout, output).await {
+            Ok(out) => out.map(Some),
+            Err(_) => {
+                if let Some(group) = group {
+                    group.terminate(&mut child).await;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Generate the result for this test when the setup command of its suite
+    /// failed with `output`.
+    pub fn setup_failed(self, output: String) -> results::Test {
+        results::Test {
+            expect_path: self.expect_file(),
+            path: self.path,
+            state: results::State::SetupFailed(output),
+            saved: false,
+            test_suite: self.test_suite,
+        }
+    }
+
+    /// Generate the result for this test when it is not run because the
+    /// suites named `deps` failed.
+    pub fn blocked(self, deps: Vec<String>) -> results::Test {
+        results::Test {
+            expect_path: self.expect_file(),
+            path: self.path,
+            state: results::State::Blocked(deps),
+            saved: false,
+            test_suite: self.test_suite,
+        }
     }
 
     /// Create a task to asynchronously execute this test. We use
@@ -87,7 +477,7 @@ impl Test {
     /// This lets us control the number of parallel running processes.
     pub async fn execute_test(self) -> Result<results::Test, RuntError> {
         let skip_path = self.skip_file();
-        if skip_path.exists() {
+        if self.skip || skip_path.exists() {
             return Ok(results::Test {
                 path: self.path,
                 expect_path: skip_path,
@@ -98,25 +488,65 @@ impl Test {
         }
 
         let expect_path = self.expect_file();
+        let xfail = self.xfail || self.xfail_file().exists();
+
+        // Re-run tests that mismatch or time out up to `retries` times.
+        // Tests that are expected to fail are not re-run.
+        let mut state = self.attempt(&expect_path).await?;
+        let mut attempts = 1;
+        while !xfail
+            && attempts <= self.retries
+            && matches!(
+                state,
+                results::State::Mismatch(..) | results::State::Timeout
+            )
+        {
+            state = self.attempt(&expect_path).await?;
+            attempts += 1;
+        }
+        if attempts > 1 && state == results::State::Correct {
+            state = results::State::Flaky(attempts);
+        }
+        if xfail {
+            state = match state {
+                results::State::Mismatch(expect, contents) => {
+                    results::State::ExpectedFail(expect, contents)
+                }
+                results::State::Correct => results::State::UnexpectedPass,
+                state => state,
+            };
+        }
 
-        let mut cmd = self.construct_command();
+        Ok(results::Test {
+            path: self.path,
+            expect_path,
+            state,
+            saved: false,
+            test_suite: self.test_suite,
+        })
+    }
 
-        match time::timeout(self.timeout, cmd.output()).await {
-            Err(_) => Ok(results::Test {
-                path: self.path,
-                expect_path,
-                state: results::State::Timeout,
-                saved: false,
-                test_suite: self.test_suite,
-            }),
-            Ok(res) => {
-                let out = res.map_err(|err| {
-                    RuntError(format!(
-                        "{}: {}",
-                        self.path.to_str().unwrap(),
-                        err
-                    ))
-                })?;
+    /// Run the test once and compare its output with the expect file at
+    /// `expect_path`.
+    async fn attempt(
+        &self,
+        expect_path: &Path,
+    ) -> Result<results::State, RuntError> {
+        let cmd = self.construct_command()?;
+
+        match self.run_command(cmd, self.get_timeout()?).await {
+            Ok(None) => Ok(results::State::Timeout),
+            Err(err) => Err(RuntError(format!(
+                "{}: {}",
+                self.path.to_string_lossy(),
+                err
+            ))),
+            Ok(Some((out, cpu_time))) => {
+                if let Some(limit) =
+                    process::exceeded_limit(&self.limits, out.status, cpu_time)
+                {
+                    return Ok(results::State::LimitExceeded(limit));
+                }
 
                 let status = out.status.code().unwrap_or(-1);
                 let stdout = String::from_utf8(out.stdout)?;
@@ -127,7 +557,7 @@ impl Test {
                     Self::format_expect_string(status, &stdout, &stderr);
 
                 // Open expect file for comparison.
-                let state = fs::read_to_string(expect_path.clone())
+                Ok(fs::read_to_string(expect_path)
                     .map(|contents| {
                         if contents == expect_string {
                             results::State::Correct
@@ -138,15 +568,7 @@ impl Test {
                             )
                         }
                     })
-                    .unwrap_or(results::State::Missing(expect_string));
-
-                Ok(results::Test {
-                    path: self.path,
-                    expect_path,
-                    state,
-                    saved: false,
-                    test_suite: self.test_suite,
-                })
+                    .unwrap_or(results::State::Missing(expect_string)))
             }
         }
     }
@@ -0,0 +1,71 @@
+//! Generation of a new runt configuration.
+use crate::{cli::InitOpts, errors::RuntError};
+use colored::*;
+use std::path::Path;
+
+/// Quote `value` as a TOML string.
+fn toml_str(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+/// Generate the contents of a commented `runt.toml` with a single test suite
+/// that runs `cmd` on the files matching `paths`. The version requirement
+/// accepts later patch releases of the installed runt.
+fn config(name: &str, paths: &[String], cmd: &str) -> String {
+    let tool = semver::Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+    let paths = paths
+        .iter()
+        .map(|path| toml_str(path))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"# Version of runt this configuration is written for.
+ver = {ver}
+
+[[tests]]
+# Name of the test suite.
+name = {name}
+# Glob patterns for the input files of the tests.
+paths = [{paths}]
+# Command to run for each input file. {{}} is replaced with its path.
+cmd = {cmd}
+# (Optional) Directory to store the .expect files. Defaults to the directory
+# containing each input file.
+# expect_dir = "out/"
+# (Optional) Timeout for each test in seconds. Defaults to 1200 seconds.
+# timeout = 60
+"#,
+        ver = toml_str(&format!("{}.{}", tool.major, tool.minor)),
+        name = toml_str(name),
+        paths = paths,
+        cmd = toml_str(cmd),
+    )
+}
+
+/// Write a new `runt.toml` to `dir` using the options in `opts`. Fails if the
+/// configuration already exists.
+pub fn init(dir: &Path, opts: &InitOpts) -> Result<(), RuntError> {
+    if opts.paths.is_empty() {
+        return Err(RuntError(
+            "runt init requires at least one --paths pattern.".to_string(),
+        ));
+    }
+    let conf_path = dir.join("runt.toml");
+    if conf_path.exists() {
+        return Err(RuntError(format!(
+            "{} already exists.",
+            conf_path.to_string_lossy()
+        )));
+    }
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&conf_path, config(&opts.name, &opts.paths, &opts.cmd))
+        .map_err(|err| {
+            RuntError(format!("{}: {}", conf_path.to_string_lossy(), err))
+        })?;
+    println!(
+        "{} {}",
+        "Created".green().bold(),
+        conf_path.to_string_lossy()
+    );
+    Ok(())
+}
@@ -7,10 +7,20 @@
 //! ## Installation
 //!
 //! To install the `runt` binary, simply run:
-//! ```
+//! ```text
 //! cargo install runt
 //! ```
 //!
+//! ## Creating a Configuration
+//! The `init` command writes a commented `runt.toml` with a single test suite
+//! whose `ver` accepts the installed version of runt, for example, `"0.4"`:
+//! ```text
+//! runt init --paths 'tests/*.calyx' --cmd 'calyx {}'
+//! ```
+//! The `--save` flag runs the new suite once and saves the generated
+//! `.expect` files. It only exits with an error if a test fails or times out.
+//! An existing `runt.toml` is never overwritten.
+//!
 //! ## Testing Model
 //! Runt's organizes tests using test suites.
 //! At the minimum, a test suite needs to be specify the input file paths as
@@ -19,20 +29,200 @@
 //! runs the command `cat {}` on every file `.txt` file in the directory
 //! `cat-tests/`.
 //! The `{}` is replaced by the path of the input file.
-//! ```
+//! ```toml
 //! [[tests]]
 //! # Name for this test suite.
 //! name = "Cat tests"
 //! # Test paths can be globs or exact.
 //! paths = [ "cat-test/*.txt" ]
+//! # (Optional) Globs for paths that should not be treated as tests.
+//! exclude_paths = [ "cat-test/helper*.txt" ]
+//! # (Optional) Fail instead of warning when a pattern in paths does not
+//! # match any files. Defaults to false.
+//! deny_empty_paths = true
 //! # Command to run on each test file. {} is replaced with input name.
 //! cmd = "cat {}"
 //! # (Optional) Directory to store the generated .expect files.
 //! expect_dir = "cat-out/"
 //! # (Optional) Timeout for tests in seconds. Defaults to 1200 seconds.
 //! timeout = 120
+//! # (Optional) Environment variables set for each command.
+//! env = { RUST_BACKTRACE = "0" }
+//! # (Optional) Working directory for each command. Defaults to the directory
+//! # containing runt.toml. "{dir}" is the directory containing the input file.
+//! cwd = "{dir}"
+//! # (Optional) Standard input for each command. Either "file" to use the
+//! # contents of the input file, "null", or a literal string. Defaults to "null".
+//! stdin = "file"
+//! # (Optional) Commands to run once before and after the tests of the suite.
+//! setup = "make build"
+//! teardown = "make clean"
 //! ```
 //!
+//! When `cwd` is set, the paths substituted into the command are rewritten so
+//! that they remain valid from the working directory.
+//!
+//! The `setup` command runs before the first test of a suite starts and the
+//! `teardown` command runs after its last test finishes. If the `setup`
+//! command fails, the tests of the suite are not run and are reported as
+//! `(setup failed)`. Both commands run in the directory containing the
+//! `runt.toml` that defines the suite, regardless of `cwd`.
+//!
+//! Runt also sets the following environment variables for each command:
+//!   - `RUNT_SUITE`: Name of the test suite.
+//!   - `RUNT_TEST_PATH`: Path of the input file.
+//!   - `RUNT_EXPECT_PATH`: Path of the `.expect` file.
+//!
+//! ## Environment Variables in the Configuration
+//! The `cmd`, `paths`, `expect_dir`, and `env` values can reference
+//! environment variables using `${VAR}` or `${VAR:-default}`. The references
+//! are expanded when the configuration is loaded:
+//! ```toml
+//! cmd = "${BUILD_DIR:-target}/bin/compiler {}"
+//! ```
+//! The default is used if the variable is unset or empty. A variable without
+//! a default that is not set is an error. Use `$${VAR}` to pass a literal
+//! `${VAR}` to the shell. References without braces, like `$VAR`, are left
+//! for the shell. In `cmd`, variables set by the `env` of the suite or of
+//! `[defaults]` take precedence over the environment of runt:
+//! ```toml
+//! env = { OUT = "build" }
+//! cmd = "compiler -o ${OUT}/{stem}.v {}"
+//! ```
+//!
+//! ## Defaults
+//! The `[defaults]` table specifies options that are inherited by every suite
+//! in the configuration unless the suite overrides them:
+//! ```toml
+//! [defaults]
+//! timeout = 60
+//! expect_dir = "out/"
+//! env = { RUST_BACKTRACE = "0" }
+//! shell = "bash"
+//! grace_period = 10
+//! # Used when --jobs and --max-futures are not specified.
+//! jobs = 4
+//! max_futures = 20
+//! ```
+//! Environment variables set by a suite are merged with the default ones.
+//! Suites that use `expect_path` do not inherit `expect_dir`. Defaults only
+//! apply to the suites of the configuration that defines them and `jobs` and
+//! `max_futures` are only read from the top-level configuration.
+//!
+//! ## Placeholders
+//! Commands can use the following placeholders which are replaced with
+//! shell-quoted values for each input file:
+//!   - `{}` or `{path}`: Path of the input file.
+//!   - `{stem}`: File name of the input file without its extension.
+//!   - `{name}`: File name of the input file.
+//!   - `{ext}`: Extension of the input file.
+//!   - `{dir}`: Directory containing the input file.
+//!   - `{suite}`: Name of the test suite.
+//!   - `{expect}`: Path of the `.expect` file.
+//!   - `{args}`: Contents of the `.args` file of the test, if any. The
+//!     contents are not quoted.
+//!
+//! A placeholder can be escaped by doubling its braces. For example, `{{}}`
+//! is passed to the command as a literal `{}`. Braces that do not form a
+//! placeholder, like `${VAR}`, are left unchanged.
+//!
+//! Earlier versions of runt substituted `{}` without quoting. Commands that
+//! quote the placeholder themselves must drop the quotes, otherwise the quotes
+//! become part of the path:
+//! ```toml
+//! # Before
+//! cmd = "cat \"{}\""
+//! # Now
+//! cmd = "cat {}"
+//! ```
+//!
+//! ## Shells and Argument Lists
+//! Commands are run using `sh -c` by default. The `shell` option of a suite
+//! selects a different shell which is used for its `cmd`, `setup`, and
+//! `teardown`. It is either the name of a shell, which is passed `-c`, or an
+//! array with the shell and its arguments:
+//! ```toml
+//! shell = "bash"
+//! shell = ["bash", "-o", "pipefail", "-c"]
+//! ```
+//! The `cmd` of a suite can also be an array which is executed directly
+//! without a shell:
+//! ```toml
+//! cmd = ["compiler", "--backend", "verilog", "{}"]
+//! ```
+//! Placeholders are substituted into each argument without quoting, so paths
+//! with spaces remain a single argument. An argument that is exactly `{args}`
+//! is split into one argument per word of the `.args` file.
+//!
+//! ## Versioning
+//! The top-level `ver` option specifies the versions of runt a configuration
+//! is compatible with. It accepts a semver requirement such as `"0.4"` or
+//! `">=0.4, <0.6"`. When `ver` is an exact version, runt fails if it is newer
+//! than runt or has a different major version, and warns if it is older but
+//! has the same major version, so `ver = "0.3.2"` is accepted with a warning
+//! by runt 0.4.1 while `ver = "0.9.0"` is an error.
+//!
+//! ## Nested Configurations
+//! A `runt.toml` can include other configurations using the top-level
+//! `include` option which accepts paths or glob patterns:
+//! ```toml
+//! include = ["frontend/runt.toml", "backend/**/runt.toml"]
+//! ```
+//! The paths, `expect_dir`, and commands of an included configuration are
+//! relative to the directory containing it. The names of its test suites are
+//! prefixed with that directory, for example, `frontend/Parser tests`.
+//! A pattern like `**/runt.toml` does not match the including configuration
+//! itself, and a configuration matched by several patterns or included by
+//! several configurations is only loaded once.
+//!
+//! ## Directive Suites
+//! Instead of specifying a single command for all the files in a suite, each
+//! file can specify its own command using directives:
+//! ```text
+//! // RUNT: compiler --flag {}
+//! // RUNT-TIMEOUT: 30
+//! // RUNT-SKIP: Not supported yet.
+//! ```
+//! Directives can be preceded by any comment syntax. Multiple `RUNT:` lines are
+//! run as a single script. Files are scanned for directives using a
+//! `[[directives]]` suite:
+//! ```toml
+//! [[directives]]
+//! name = "Compiler tests"
+//! paths = [ "tests/*.futil" ]
+//! # (Optional) Same as the corresponding options of [[tests]].
+//! expect_dir = "out/"
+//! timeout = 120
+//! cwd = "{dir}"
+//! env = { RUST_BACKTRACE = "0" }
+//! shell = "bash"
+//! grace_period = 10
+//! limits = { memory = "4G" }
+//! ```
+//! Files without a `RUNT:` directive are not tests. Directive suites inherit
+//! `[defaults]` like other suites.
+//!
+//! ## Matrix Suites
+//! A test suite can define a `matrix` of values to run the same tests with
+//! different parameters:
+//! ```toml
+//! [[tests]]
+//! name = "Compile"
+//! paths = [ "tests/*.futil" ]
+//! cmd = "compiler {opt} -b {backend} {}"
+//! expect_dir = "out/{backend}{opt}"
+//! matrix = { opt = ["-O0", "-O2"], backend = ["verilog", "firrtl"] }
+//! ```
+//! Runt expands the suite into one suite for each combination of values and
+//! replaces `{key}` in `cmd` and `expect_dir` with the value of `key`.
+//! Each suite is named using its values, for example,
+//! `Compile[backend=verilog,opt=-O2]`. Unlike placeholders, the values are
+//! substituted verbatim without quoting, so a value like `"-O2 -g"` passes two
+//! arguments to a shell command. In a `cmd` given as an array, a value stays
+//! part of the argument that contains it. Keys cannot be the names of
+//! placeholders such as `name` or `path`, and a matrix without keys or a key
+//! without values is an error.
+//!
 //! ## Running a Test Suite
 //! Runt's command line interface is used to run and interact with a Runt
 //! test suite.
@@ -51,6 +241,14 @@
 //!
 //! According to Runt, we have 2 failing and 2 missing tests.
 //!
+//! ## Checking a Configuration
+//! `runt check` validates the `runt.toml` without running any tests. It
+//! reports unknown keys with their line and column, lists each test suite
+//! with the number of tests it matches, and reports duplicate suite names,
+//! `expect_dir`s that do not exist, tests that share an `.expect` file, and
+//! suites whose tests cannot be gathered, for example, because of an invalid
+//! glob pattern.
+//!
 //! ## Filters
 //!
 //! A complete runt configuration might have hundreds of tests. Runt provides
@@ -90,6 +288,37 @@
 //! Note that runt reports 0 passing tests because it is not running the test
 //! suite with the previously passing tests.
 //!
+//! ## Tags
+//! Test suites can be labelled using the `tags` option:
+//! ```toml
+//! [[tests]]
+//! name = "Solver tests"
+//! tags = ["slow", "z3"]
+//! ```
+//! The `--tag` option only runs suites with the given tag and `--skip-tag`
+//! skips suites with the given tag. Both options can be repeated. A suite is
+//! run if it has any of the tags passed to `--tag` and none of the tags passed
+//! to `--skip-tag`:
+//! ```text
+//! runt --skip-tag slow
+//! runt --tag nightly --skip-tag z3
+//! ```
+//!
+//! ## Suite Dependencies
+//! The tests of a suite can wait for other suites to finish using the
+//! `depends_on` option:
+//! ```toml
+//! [[tests]]
+//! name = "Simulate examples"
+//! depends_on = ["Compile examples"]
+//! ```
+//! If a test of a dependency fails, the tests of the dependent suite are not
+//! run and are reported as `(blocked by <suite>)`. A dependency on a matrix
+//! suite waits for all of its expanded suites. The suites that a suite
+//! selected using `--tag` or `--skip-tag` depends on are run as well, even if
+//! they do not match the tags. The `-i` and `-x` filters still apply to their
+//! tests. Cyclic dependencies are reported as errors.
+//!
 //! ## Viewing diffs and Saving .expect Files
 //!
 //! Under the hood, `runt` uses `.expect` files to test the outputs of running
@@ -99,6 +328,20 @@
 //! For example, we can view the `.expect` files for the "Cat tests" suite
 //! under `cat-out`.
 //!
+//! By default, the `.expect` file of `dir/file.txt` is `<expect_dir>/file.expect`
+//! which means that inputs with the same file name in different directories
+//! share an `.expect` file. Runt warns about such tests before running them.
+//! The `expect_path` option specifies a template for the path of the `.expect`
+//! files instead:
+//! ```toml
+//! expect_path = "golden/{suite}/{relpath}.expect"
+//! ```
+//! The template can use the `{suite}` and `{relpath}` (path of the input file
+//! without its extension) placeholders along with the `{path}`, `{stem}`,
+//! `{name}`, `{ext}`, and `{dir}` placeholders. Values are not quoted. In an
+//! included configuration, the template and the paths substituted into it are
+//! relative to the directory of that configuration.
+//!
 //! Runt is also capable of showing diffs for failing and missing tests using
 //! the `-d` flag.
 //! For example, we can run:
@@ -115,14 +358,81 @@
 //!
 //! Both the `-d` and `-s` flags work with the filtering flags.
 //!
+//! ## Embedding Runt
+//! Runt's executor can run tests from sources other than a `runt.toml` file.
+//! Implement the [picker::Picker] trait to generate test suites and execute
+//! them using [executor::Context].
+//!
+//! ## Sidecar Files
+//!
+//! Individual tests can be configured using files next to their `.expect`
+//! file:
+//!   - `.skip`: The test is not run.
+//!   - `.args`: Extra arguments substituted for the `{args}` placeholder.
+//!   - `.timeout`: Timeout for the test in seconds.
+//!   - `.env`: Extra environment variables specified as `KEY=VALUE` lines.
+//!   - `.xfail`: The test is expected to fail.
+//!
+//! ## Expected Failures
+//!
+//! Tests that are known to fail can be marked using a `.xfail` file or the
+//! `xfail` option of a test suite which accepts glob patterns:
+//! ```toml
+//! xfail = ["tests/bug-*.futil"]
+//! ```
+//! An expected failure still runs. If its output does not match the `.expect`
+//! file, it is reported as an `(expected failure)` and does not fail the run.
+//! If its output matches, it is reported as `(unexpectedly passing)` and fails
+//! the run so that the marker can be removed. Expected failures are not
+//! re-run or saved.
+//!
 //! ## Timeouts
 //!
 //! Test suites can require a default timeout for each individual test.
 //! When left unspecified, Runt will use 20 minutes as the default.
 //!
+//! Each test runs in its own process group. When a test times out, runt is
+//! interrupted using Ctrl-C, or the run stops because of an error, runt sends
+//! `SIGTERM` to the process group of the test so that the processes started
+//! by the command are also stopped. If the processes are still running after
+//! the grace period of the suite, runt sends `SIGKILL`. The grace period
+//! defaults to 5 seconds and is set using the `grace_period` option of a suite
+//! or `[defaults]`:
+//! ```toml
+//! grace_period = 10
+//! ```
+//!
+//! ## Resource Limits
+//!
+//! The `limits` option of a test suite limits the resources used by each
+//! process of a test command:
+//! ```toml
+//! limits = { memory = "4G", cpu_seconds = 60, file_size = "1G" }
+//! ```
+//! Sizes accept the suffixes `K`, `M`, `G`, and `T`. The limits can also be
+//! set in `[defaults]` and suites override individual limits. A test that is
+//! killed by a limit is reported as `(<limit> limit exceeded)`, for example,
+//! `(cpu_seconds limit exceeded)`. Runt only reports a limit when it is
+//! certain that the limit stopped the command: the command was killed by
+//! `SIGXCPU` or `SIGXFSZ`, or by `SIGKILL` after using more processor time
+//! than `cpu_seconds`. A shell that runs the command reports such signals as
+//! an exit code, so use `exec` for the last command of a shell command.
+//! Allocations that exceed the memory limit fail and the resulting crash or
+//! error is compared with the expect file as usual.
+//!
+//! ## Retries
+//!
+//! The `retries` option of a test suite re-runs tests that mismatch or time
+//! out up to the given number of times. The `--retries` flag overrides the
+//! option for all suites. Tests that pass on a later attempt are reported as
+//! `flaky` and counted separately in the summary. Flaky tests do not fail the
+//! run.
+//!
 //! [runt-suite]: https://github.com/rachitnigam/runt/tree/master/cli-test
+pub mod check;
 pub mod cli;
 pub mod errors;
 pub mod executor;
+pub mod init;
 pub mod picker;
 pub mod printer;
@@ -1,7 +1,6 @@
 use runt::{
-    cli, errors,
-    executor::{self, suite},
-    picker::toml::Config,
+    check, cli, errors, executor, init,
+    picker::{toml::Config, Picker},
 };
 
 use cli::Opts;
@@ -9,31 +8,46 @@ use errors::RuntError;
 use regex::Regex;
 use tokio::runtime;
 
-fn dry_run(suites: Vec<suite::Suite>) {
+fn dry_run(ctx: &executor::Context) -> Result<(), RuntError> {
     use colored::*;
-    for suite in suites {
-        let cmd = suite.config.cmd.clone();
-        for path in suite.paths {
-            println!(
-                "{}{}{}\n  {}",
-                suite.config.name.blue(),
-                ":".blue(),
-                path.to_string_lossy().blue(),
-                cmd.replace("{}", path.to_str().unwrap())
-            );
-        }
+    for test in ctx.exec.tests() {
+        println!(
+            "{}{}{}\n  {}",
+            test.suite_name.blue(),
+            ":".blue(),
+            test.path.to_string_lossy().blue(),
+            test.command_string()?.to_string_lossy()
+        );
     }
+    Ok(())
 }
 
 fn run() -> Result<i32, RuntError> {
-    let opts: Opts = argh::from_env();
+    let mut opts: Opts = argh::from_env();
 
     if opts.version {
         println!(env!("CARGO_PKG_VERSION"));
         return Ok(0);
     }
 
-    let Config { tests, .. } = Config::from_path(&opts.dir)?;
+    match &opts.mode {
+        Some(cli::Mode::Check(check)) => {
+            return check::check(check.dir.as_ref().unwrap_or(&opts.dir));
+        }
+        Some(cli::Mode::Init(init)) => {
+            let dir = init.dir.clone().unwrap_or_else(|| opts.dir.clone());
+            init::init(&dir, init)?;
+            if !init.save {
+                return Ok(0);
+            }
+            // Run the new suite once to save the initial expect files.
+            opts.save = true;
+            opts.dir = dir;
+        }
+        None => (),
+    }
+
+    let config = Config::from_path(&opts.dir)?;
 
     // Get the include and exclude regexes.
     let include = opts
@@ -49,24 +63,52 @@ fn run() -> Result<i32, RuntError> {
     // Switch to directory containing runt.toml.
     std::env::set_current_dir(&opts.dir)?;
 
-    let suites: Vec<suite::Suite> = tests
-        .into_iter()
-        .map(|c| {
-            suite::Suite::from(c)
-                .with_filters(include.as_ref(), exclude.as_ref())
-        })
-        .collect();
+    let suites = executor::suite::select_by_tags(
+        config.suites()?,
+        &opts.tags,
+        &opts.skip_tags,
+    )
+    .into_iter()
+    .map(|mut s| {
+        if let Some(retries) = opts.retries {
+            s.config.retries = retries;
+        }
+        s.with_filters(include.as_ref(), exclude.as_ref())
+    })
+    .collect();
+
+    let ctx = executor::Context::from(
+        suites,
+        opts.max_futures
+            .or(config.defaults.max_futures)
+            .unwrap_or(50),
+    )?;
+
+    for (expect, tests) in ctx.expect_collisions() {
+        errors::warn(format!(
+            "Tests share the expect file {}: {}",
+            expect.to_string_lossy(),
+            tests
+                .iter()
+                .map(|test| test.full_name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
 
     // Print out the commands for each test in dry run mode.
     if opts.dry_run {
-        dry_run(suites);
+        dry_run(&ctx)?;
         return Ok(0);
     }
 
-    let ctx = executor::Context::from(suites, opts.max_futures.unwrap_or(50));
     let runtime = runtime::Builder::new_multi_thread()
         .enable_all()
-        .worker_threads(opts.jobs_limit.unwrap_or_else(num_cpus::get))
+        .worker_threads(
+            opts.jobs_limit
+                .or(config.defaults.jobs)
+                .unwrap_or_else(num_cpus::get),
+        )
         .build()
         .unwrap();
 
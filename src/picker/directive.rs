@@ -0,0 +1,229 @@
+//! A picker that gathers tests by scanning input files for directives, in the
+//! style of LLVM's lit.
+//! Each input file specifies how it is tested using lines of the form:
+//! ```text
+//! // RUNT: cmd --flag {}
+//! // RUNT-TIMEOUT: 30
+//! // RUNT-SKIP: reason
+//! ```
+//! The directives can be preceded by any comment syntax. Input files without
+//! a `RUNT:` directive are not tests.
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{
+    toml::{Defaults, LimitsConfig, ShellConfig},
+    Picker,
+};
+use crate::{errors::RuntError, executor::suite};
+
+/// Configuration for a test suite whose commands are specified by the
+/// directives in its input files.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Name of this TestSuite
+    pub name: String,
+    /// Paths of input files to scan for directives.
+    pub paths: Vec<String>,
+    /// Optional directory to store the generated .expect files.
+    pub expect_dir: Option<PathBuf>,
+    /// Optional timeout used for files without a `RUNT-TIMEOUT` directive.
+    pub timeout: Option<u64>,
+    /// Optional environment variables set for each test command.
+    pub env: Option<HashMap<String, String>>,
+    /// Optional shell used to run the directives. Defaults to `sh -c`.
+    pub shell: Option<ShellConfig>,
+    /// Optional time in seconds between SIGTERM and SIGKILL when a test is
+    /// terminated.
+    pub grace_period: Option<u64>,
+    /// Optional resource limits of each test command.
+    pub limits: Option<LimitsConfig>,
+    /// Optional working directory for each test command. The special value
+    /// `{dir}` runs each command in the directory containing its input file.
+    pub cwd: Option<String>,
+    /// Optional tags used to select the suite with `--tag` and `--skip-tag`.
+    pub tags: Option<Vec<String>>,
+    /// Optional names of suites that must finish before the tests of this
+    /// suite start.
+    pub depends_on: Option<Vec<String>>,
+    /// Directory of the configuration that defines this suite relative to
+    /// the root configuration.
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+/// Directives specified in an input file.
+#[derive(Default)]
+struct Directives {
+    /// Lines of the command to run.
+    cmd: Vec<String>,
+    /// Timeout for the test.
+    timeout: Option<u64>,
+    /// The test is skipped.
+    skip: bool,
+}
+
+impl Directives {
+    /// Regex matching a directive line. The first group is the kind of the
+    /// directive and the second its value.
+    fn regex() -> Regex {
+        Regex::new(r"^\W*RUNT(-[A-Z]+)?:(.*)$").expect("Invalid regex")
+    }
+
+    /// Parse the directives in `contents` of the file at `path` using the
+    /// `directive` regex from [Directives::regex].
+    fn parse(
+        path: &Path,
+        contents: &str,
+        directive: &Regex,
+    ) -> Result<Self, RuntError> {
+        let mut dirs = Directives::default();
+        for (lineno, line) in contents.lines().enumerate() {
+            let caps = match directive.captures(line) {
+                Some(caps) => caps,
+                None => continue,
+            };
+            let value = caps[2].trim();
+            let err = |msg: &str| {
+                RuntError(format!(
+                    "{}:{}: {}",
+                    path.to_string_lossy(),
+                    lineno + 1,
+                    msg
+                ))
+            };
+            match caps.get(1).map(|kind| kind.as_str()) {
+                None => dirs.cmd.push(value.to_string()),
+                Some("-TIMEOUT") => {
+                    dirs.timeout = Some(value.parse().map_err(|_| {
+                        err("RUNT-TIMEOUT expects a number of seconds")
+                    })?)
+                }
+                Some("-SKIP") => dirs.skip = true,
+                Some(kind) => {
+                    return Err(err(&format!("Unknown directive RUNT{}", kind)))
+                }
+            }
+        }
+        Ok(dirs)
+    }
+}
+
+impl Config {
+    /// Expand the environment variables in the `paths`, `expect_dir`, and
+    /// `env` values.
+    pub(crate) fn interpolate(mut self) -> Result<Self, RuntError> {
+        let in_suite =
+            |err: RuntError| RuntError(format!("{}: {}", self.name, err));
+        let paths = self
+            .paths
+            .iter()
+            .map(|pattern| super::interpolate(pattern))
+            .collect::<Result<_, _>>()
+            .map_err(in_suite)?;
+        let expect_dir = self
+            .expect_dir
+            .as_deref()
+            .map(super::interpolate_path)
+            .transpose()
+            .map_err(in_suite)?;
+        let env = self
+            .env
+            .clone()
+            .map(super::toml::interpolate_env)
+            .transpose()
+            .map_err(in_suite)?;
+        self.paths = paths;
+        self.expect_dir = expect_dir;
+        self.env = env;
+        Ok(self)
+    }
+
+    /// Fill in the options that are not specified by this suite using
+    /// `defaults`.
+    pub(crate) fn inherit(mut self, defaults: &Defaults) -> Self {
+        self.timeout = self.timeout.or(defaults.timeout);
+        self.grace_period = self.grace_period.or(defaults.grace_period);
+        self.expect_dir =
+            self.expect_dir.or_else(|| defaults.expect_dir.clone());
+        self.env = defaults.inherit_env(self.env.take());
+        self.shell = self.shell.or_else(|| defaults.shell.clone());
+        self.limits = defaults.inherit_limits(self.limits.take());
+        self
+    }
+
+    /// Rebase a suite defined in the configuration under the directory `dir`
+    /// so that its paths are relative to the including configuration.
+    /// See [Relative::rebase](super::Relative::rebase).
+    pub(crate) fn rebase(mut self, dir: &Path) -> Self {
+        super::Relative {
+            name: &mut self.name,
+            paths: &mut self.paths,
+            expect_dir: &mut self.expect_dir,
+            depends_on: &mut self.depends_on,
+            cwd: &mut self.cwd,
+            dir: &mut self.dir,
+        }
+        .rebase(dir);
+        self
+    }
+}
+
+impl Picker for Config {
+    /// Scan the input files for directives and generate a test suite for
+    /// each file that contains a `RUNT:` directive.
+    /// Multiple `RUNT:` directives in a file are run as a single script.
+    fn suites(&self) -> Result<Vec<suite::Suite>, RuntError> {
+        let paths = super::gather_paths(&self.name, &self.paths, &[], false)?;
+        let shell = ShellConfig::resolve(self.shell.clone(), &self.name)?;
+        let limits = self
+            .limits
+            .as_ref()
+            .map(|limits| limits.parse(&self.name))
+            .transpose()?
+            .unwrap_or_default();
+        let directive = Directives::regex();
+        let mut suites = Vec::new();
+        for path in paths {
+            let contents = std::fs::read(&path).map_err(|err| {
+                RuntError(format!("{}: {}", path.to_string_lossy(), err))
+            })?;
+            let dirs = Directives::parse(
+                &path,
+                &String::from_utf8_lossy(&contents),
+                &directive,
+            )?;
+            if dirs.cmd.is_empty() {
+                continue;
+            }
+
+            let timeout = dirs.timeout.or(self.timeout).unwrap_or(1200);
+            suites.push(suite::Suite {
+                paths: vec![path],
+                config: suite::Config {
+                    expect_dir: self.expect_dir.clone(),
+                    dir: self.dir.clone(),
+                    timeout: Duration::from_secs(timeout),
+                    cwd: super::parse_cwd(self.cwd.as_deref()),
+                    shell: shell.clone(),
+                    env: self.env.clone().unwrap_or_default(),
+                    limits: limits.clone(),
+                    grace_period: Duration::from_secs(
+                        self.grace_period.unwrap_or(5),
+                    ),
+                    skip: dirs.skip,
+                    tags: self.tags.clone().unwrap_or_default(),
+                    depends_on: self.depends_on.clone().unwrap_or_default(),
+                    ..suite::Config::new(self.name.clone(), dirs.cmd.join("\n"))
+                },
+            });
+        }
+        Ok(suites)
+    }
+}
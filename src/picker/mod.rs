@@ -1,3 +1,253 @@
 //! A picker defines a mechanism to gather tests from a test configuration.
+use crate::{
+    errors::{self, RuntError},
+    executor::suite,
+};
+use std::path::{Path, PathBuf};
 
+pub mod directive;
 pub mod toml;
+
+/// A source of test suites.
+/// Implement this trait to execute tests from sources other than a
+/// `runt.toml` file using runt's [Context](crate::executor::Context):
+/// ```no_run
+/// use runt::{errors::RuntError, executor::suite, picker::Picker};
+///
+/// struct Generated;
+///
+/// impl Picker for Generated {
+///     fn suites(&self) -> Result<Vec<suite::Suite>, RuntError> {
+///         Ok(vec![suite::Suite {
+///             paths: vec!["tests/a.txt".into()],
+///             config: suite::Config::new("Generated".into(), "cat {}".into()),
+///         }])
+///     }
+/// }
+/// ```
+pub trait Picker {
+    /// Gather the test suites to execute.
+    fn suites(&self) -> Result<Vec<suite::Suite>, RuntError>;
+}
+
+/// Expand the references to environment variables of the form `${VAR}` and
+/// `${VAR:-default}` in `value`. The default is used if the variable is unset
+/// or empty. A literal `${` is written as `$${`.
+pub(crate) fn interpolate(value: &str) -> Result<String, RuntError> {
+    interpolate_with(value, |name| std::env::var(name).ok())
+}
+
+/// Expand the references to variables in `value` like [interpolate] using
+/// `lookup` to find the value of each variable.
+pub(crate) fn interpolate_with<F>(
+    value: &str,
+    lookup: F,
+) -> Result<String, RuntError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut buf = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        buf.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(tail) = rest.strip_prefix("$${") {
+            buf.push_str("${");
+            rest = tail;
+            continue;
+        }
+        let tail = match rest.strip_prefix("${") {
+            Some(tail) => tail,
+            None => {
+                buf.push('$');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let end = tail.find('}').ok_or_else(|| {
+            RuntError(format!("Unterminated `${{` in `{}`.", value))
+        })?;
+        let (name, default) = match tail[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&tail[..end], None),
+        };
+        if name.is_empty()
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(RuntError(format!(
+                "Invalid environment variable `${{{}}}` in `{}`.",
+                &tail[..end],
+                value
+            )));
+        }
+        let var = match (lookup(name), default) {
+            (Some(var), Some(default)) if var.is_empty() => {
+                default.to_string()
+            }
+            (Some(var), _) => var,
+            (None, Some(default)) => default.to_string(),
+            (None, None) => {
+                return Err(RuntError(format!(
+                    "Environment variable `{}` is not set. Use `${{{}:-default}}` to provide a default.",
+                    name, name
+                )))
+            }
+        };
+        buf.push_str(&var);
+        rest = &tail[end + 1..];
+    }
+    buf.push_str(rest);
+    Ok(buf)
+}
+
+/// Expand the environment variables in a path using [interpolate].
+pub(crate) fn interpolate_path(path: &Path) -> Result<PathBuf, RuntError> {
+    interpolate(&path.to_string_lossy()).map(PathBuf::from)
+}
+
+/// Parse a size in bytes with an optional `K`, `M`, `G`, or `T` suffix which
+/// are powers of 1024, for example, `512M` or `4G`.
+pub(crate) fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let size = size.strip_suffix(['B', 'b']).unwrap_or(size);
+    let (num, scale) = match size.char_indices().last()? {
+        (idx, 'K' | 'k') => (&size[..idx], 1 << 10),
+        (idx, 'M' | 'm') => (&size[..idx], 1 << 20),
+        (idx, 'G' | 'g') => (&size[..idx], 1 << 30),
+        (idx, 'T' | 't') => (&size[..idx], 1 << 40),
+        _ => (size, 1),
+    };
+    num.trim().parse::<u64>().ok()?.checked_mul(scale)
+}
+
+/// Parse the working directory of a test suite. The special value `{dir}`
+/// refers to the directory containing the input file of each test.
+pub(crate) fn parse_cwd(cwd: Option<&str>) -> suite::Cwd {
+    match cwd {
+        None => suite::Cwd::Root,
+        Some("{dir}") => suite::Cwd::InputDir,
+        Some(dir) => suite::Cwd::Dir(dir.into()),
+    }
+}
+
+/// Options of a suite configuration that are relative to the configuration
+/// file defining the suite. Used by the pickers to rebase suites loaded from
+/// included configurations.
+pub(crate) struct Relative<'a> {
+    pub name: &'a mut String,
+    pub paths: &'a mut Vec<String>,
+    pub expect_dir: &'a mut Option<PathBuf>,
+    pub depends_on: &'a mut Option<Vec<String>>,
+    pub cwd: &'a mut Option<String>,
+    /// Directory of the configuration that defines the suite.
+    pub dir: &'a mut PathBuf,
+}
+
+impl Relative<'_> {
+    /// Rebase the options of a suite defined in the configuration under the
+    /// directory `dir` so that they are relative to the including
+    /// configuration. The name of the suite and its dependencies are prefixed
+    /// with `dir` and its commands run in `dir` unless it specifies a working
+    /// directory.
+    pub fn rebase(self, dir: &Path) {
+        if dir.as_os_str().is_empty() {
+            return;
+        }
+        let prefix = |name: &str| format!("{}/{}", dir.to_string_lossy(), name);
+        *self.name = prefix(self.name);
+        rebase_patterns(dir, self.paths);
+        *self.expect_dir =
+            self.expect_dir.take().map(|expect| dir.join(expect));
+        if let Some(names) = self.depends_on {
+            names.iter_mut().for_each(|name| *name = prefix(name));
+        }
+        *self.cwd = match self.cwd.take() {
+            Some(cwd) if cwd == "{dir}" => Some(cwd),
+            Some(cwd) => Some(dir.join(cwd).to_string_lossy().to_string()),
+            None => Some(dir.to_string_lossy().to_string()),
+        };
+        *self.dir = dir.join(&self.dir).components().collect();
+    }
+}
+
+/// Prefix the glob `patterns` with the directory `dir`.
+pub(crate) fn rebase_patterns(dir: &Path, patterns: &mut [String]) {
+    for pattern in patterns {
+        *pattern = dir.join(&pattern).to_string_lossy().to_string();
+    }
+}
+
+/// Error for an invalid glob `pattern` of the test suite `name`.
+fn pattern_error(
+    name: &str,
+    pattern: &str,
+    err: glob::PatternError,
+) -> RuntError {
+    RuntError(format!(
+        "{}: Invalid glob pattern `{}`: {}",
+        name, pattern, err
+    ))
+}
+
+/// Compile the glob `patterns` of the test suite `name`.
+pub(crate) fn compile_patterns(
+    name: &str,
+    patterns: &[String],
+) -> Result<Vec<glob::Pattern>, RuntError> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern)
+                .map_err(|err| pattern_error(name, pattern, err))
+        })
+        .collect()
+}
+
+/// Gather the paths matching the glob patterns in `paths` but not the ones
+/// in `exclude` for the test suite `name`.
+/// Warns about patterns in `paths` that do not match any files or errors if
+/// `deny_empty` is set.
+pub(crate) fn gather_paths(
+    name: &str,
+    paths: &[String],
+    exclude: &[String],
+    deny_empty: bool,
+) -> Result<Vec<PathBuf>, RuntError> {
+    let globbed = paths
+        .iter()
+        .map(|pattern| {
+            glob::glob(pattern)
+                .map_err(|err| pattern_error(name, pattern, err))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| {
+                    RuntError(format!(
+                        "{}: Failed to read {} matched by `{}`: {}",
+                        name,
+                        err.path().to_string_lossy(),
+                        pattern,
+                        err.error()
+                    ))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (pattern, paths) in paths.iter().zip(&globbed) {
+        if paths.is_empty() {
+            let msg = format!(
+                "{}: Pattern `{}` does not match any files.",
+                name, pattern
+            );
+            if deny_empty {
+                return Err(RuntError(msg));
+            }
+            errors::warn(msg);
+        }
+    }
+
+    let exclude = compile_patterns(name, exclude)?;
+    Ok(globbed
+        .into_iter()
+        .flatten()
+        .filter(|path| !exclude.iter().any(|pat| pat.matches_path(path)))
+        .collect())
+}
@@ -1,95 +1,797 @@
 //! The default picker for runt test suites that gathers tests to run from a
 //! runt.toml file.
+use semver::{Version, VersionReq};
 use serde::Deserialize;
 use std::{
-    path::{Path, PathBuf},
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::TryFrom,
+    path::{Component, Path, PathBuf},
     time::Duration,
 };
 
+use super::{
+    directive, interpolate, interpolate_path, interpolate_with,
+    rebase_patterns, Picker, Relative,
+};
 use crate::{errors, executor::suite};
 
 /// Configuration for a single runt run.
 /// Tests suites for this runt configuration
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     /// Version of the runt tool this configuration is compatible with.
+    /// Either an exact version or a semver requirement.
     pub ver: String,
+    /// Optional paths or glob patterns of nested runt configurations.
+    /// Paths are relative to the directory containing this configuration.
+    pub include: Option<Vec<String>>,
+    /// Default options for the test suites of this configuration.
+    #[serde(default)]
+    pub defaults: Defaults,
     /// Test suite configurations.
+    #[serde(default)]
     pub tests: Vec<SuiteConfig>,
+    /// Test suites whose commands are specified by directives in the input
+    /// files.
+    #[serde(default)]
+    pub directives: Vec<directive::Config>,
+}
+
+/// Default options inherited by the test suites of a configuration unless
+/// they override them.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    /// Default timeout in seconds.
+    pub timeout: Option<u64>,
+    /// Default directory to store the generated .expect files.
+    pub expect_dir: Option<PathBuf>,
+    /// Environment variables set for each test command. Variables set by a
+    /// suite override these.
+    pub env: Option<HashMap<String, String>>,
+    /// Default shell used to run the commands.
+    pub shell: Option<ShellConfig>,
+    /// Default time in seconds between SIGTERM and SIGKILL when a test is
+    /// terminated.
+    pub grace_period: Option<u64>,
+    /// Default resource limits of each test command. Suites override
+    /// individual limits.
+    pub limits: Option<LimitsConfig>,
+    /// Number of jobs to run in parallel if `--jobs` is not specified.
+    pub jobs: Option<usize>,
+    /// Maximum number of futures if `--max-futures` is not specified.
+    pub max_futures: Option<usize>,
+}
+
+/// Resource limits of the commands of a test suite.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Maximum size of the virtual memory, for example, `"4G"`.
+    pub memory: Option<String>,
+    /// Maximum processor time in seconds.
+    pub cpu_seconds: Option<u64>,
+    /// Maximum size of a written file, for example, `"1G"`.
+    pub file_size: Option<String>,
+}
+
+impl LimitsConfig {
+    /// Parse the limits of the suite `name`.
+    pub(crate) fn parse(
+        &self,
+        name: &str,
+    ) -> Result<suite::Limits, errors::RuntError> {
+        let size = |limit: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| {
+                    super::parse_size(value).ok_or_else(|| {
+                        errors::RuntError(format!(
+                            "{}: Invalid {} limit `{}`. Expected a size like `512M` or `4G`.",
+                            name, limit, value
+                        ))
+                    })
+                })
+                .transpose()
+        };
+        Ok(suite::Limits {
+            memory: size("memory", &self.memory)?,
+            cpu_seconds: self.cpu_seconds,
+            file_size: size("file_size", &self.file_size)?,
+        })
+    }
+}
+
+/// Shell used to run the commands of a test suite.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ShellConfig {
+    /// Name of a shell which is run with `-c`.
+    Name(String),
+    /// A shell and its arguments. The command is passed as the last argument.
+    Argv(Vec<String>),
+}
+
+impl ShellConfig {
+    /// Resolve the `shell` of the suite named `suite` into the shell and its
+    /// arguments. Defaults to `sh -c`.
+    pub(crate) fn resolve(
+        shell: Option<Self>,
+        suite: &str,
+    ) -> Result<Vec<String>, errors::RuntError> {
+        match shell {
+            None => Ok(vec!["sh".to_string(), "-c".to_string()]),
+            Some(ShellConfig::Name(name)) => Ok(vec![name, "-c".to_string()]),
+            Some(ShellConfig::Argv(argv)) if argv.is_empty() => Err(
+                errors::RuntError(format!("{}: shell cannot be empty.", suite)),
+            ),
+            Some(ShellConfig::Argv(argv)) => Ok(argv),
+        }
+    }
 }
 
 /// Configuration for a test suite.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SuiteConfig {
     /// Name of this TestSuite
     pub name: String,
     /// Paths of input files.
     pub paths: Vec<String>,
+    /// Optional glob patterns for paths that are not tests.
+    pub exclude_paths: Option<Vec<String>>,
+    /// Error instead of warning when a pattern in `paths` does not match any
+    /// files.
+    #[serde(default)]
+    pub deny_empty_paths: bool,
     /// Command to execute. The pattern `{}` in this string is replaced with
-    /// the matching path.
-    pub cmd: String,
+    /// the matching path. An array of strings is executed without a shell.
+    pub cmd: suite::Cmd,
+    /// Optional shell used to run the command. Either the name of a shell
+    /// which is run with `-c` or an array with the shell and its arguments.
+    pub shell: Option<ShellConfig>,
     /// Optional directory to store the generated .expect files.
     pub expect_dir: Option<PathBuf>,
+    /// Optional template for the path of the generated .expect files. Cannot
+    /// be used with `expect_dir`.
+    pub expect_path: Option<String>,
     /// Optional timeout
     pub timeout: Option<u64>,
+    /// Optional environment variables set for each test command.
+    pub env: Option<HashMap<String, String>>,
+    /// Optional working directory for each test command. The special value
+    /// `{dir}` runs each command in the directory containing its input file.
+    pub cwd: Option<String>,
+    /// Optional standard input for each test command. Either `"file"` to
+    /// use the contents of the input file, `"null"`, or a literal string.
+    pub stdin: Option<String>,
+    /// Optional command to run once before the tests of this suite.
+    pub setup: Option<String>,
+    /// Optional command to run once after the tests of this suite.
+    pub teardown: Option<String>,
+    /// Optional matrix of values. The suite is expanded into one suite for
+    /// each combination of values and the pattern `{key}` in `cmd`,
+    /// `expect_dir`, and `expect_path` is replaced with the value of `key`.
+    pub matrix: Option<BTreeMap<String, Vec<String>>>,
+    /// Optional tags used to select test suites with `--tag` and
+    /// `--skip-tag`.
+    pub tags: Option<Vec<String>>,
+    /// Optional names of suites that must finish before the tests of this
+    /// suite start.
+    pub depends_on: Option<Vec<String>>,
+    /// Optional number of times a test that mismatches or times out is
+    /// re-run.
+    pub retries: Option<u32>,
+    /// Optional glob patterns for the tests that are expected to fail.
+    pub xfail: Option<Vec<String>>,
+    /// Optional time in seconds between SIGTERM and SIGKILL when a test is
+    /// terminated.
+    pub grace_period: Option<u64>,
+    /// Optional resource limits of each test command.
+    pub limits: Option<LimitsConfig>,
+    /// Directory of the configuration that defines this suite relative to
+    /// the root configuration.
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl Defaults {
+    /// Expand the environment variables in the `expect_dir` and `env` values.
+    fn interpolate(mut self) -> Result<Self, errors::RuntError> {
+        self.expect_dir = self
+            .expect_dir
+            .as_deref()
+            .map(interpolate_path)
+            .transpose()?;
+        self.env = self.env.map(interpolate_env).transpose()?;
+        Ok(self)
+    }
+}
+
+impl Defaults {
+    /// Merge the environment variables of a suite, `env`, with the default
+    /// ones. The variables of the suite take precedence.
+    pub(crate) fn inherit_env(
+        &self,
+        env: Option<HashMap<String, String>>,
+    ) -> Option<HashMap<String, String>> {
+        match &self.env {
+            Some(default) => {
+                let mut merged = default.clone();
+                merged.extend(env.unwrap_or_default());
+                Some(merged)
+            }
+            None => env,
+        }
+    }
+
+    /// Fill in the resource limits that are not specified by a suite,
+    /// `limits`, using the default limits.
+    pub(crate) fn inherit_limits(
+        &self,
+        limits: Option<LimitsConfig>,
+    ) -> Option<LimitsConfig> {
+        let default = match &self.limits {
+            Some(default) => default,
+            None => return limits,
+        };
+        let limits = limits.unwrap_or_default();
+        Some(LimitsConfig {
+            memory: limits.memory.or_else(|| default.memory.clone()),
+            cpu_seconds: limits.cpu_seconds.or(default.cpu_seconds),
+            file_size: limits.file_size.or_else(|| default.file_size.clone()),
+        })
+    }
+}
+
+/// Expand the environment variables in the values of `env`.
+pub(crate) fn interpolate_env(
+    env: HashMap<String, String>,
+) -> Result<HashMap<String, String>, errors::RuntError> {
+    env.into_iter()
+        .map(|(key, value)| Ok((key, interpolate(&value)?)))
+        .collect()
+}
+
+impl SuiteConfig {
+    /// Expand the environment variables in the `cmd`, `paths`,
+    /// `expect_dir`, and `env` values. Variables in `cmd` are looked up in
+    /// the `env` of the suite and `defaults` before the environment of runt.
+    fn interpolate(
+        mut self,
+        defaults: &Defaults,
+    ) -> Result<Self, errors::RuntError> {
+        let name = self.name.clone();
+        let in_suite = |err: errors::RuntError| {
+            errors::RuntError(format!("{}: {}", name, err))
+        };
+        self.env = self
+            .env
+            .map(interpolate_env)
+            .transpose()
+            .map_err(in_suite)?;
+        let (env, default_env) = (&self.env, &defaults.env);
+        let lookup = |var: &str| {
+            env.iter()
+                .chain(default_env)
+                .find_map(|env| env.get(var).cloned())
+                .or_else(|| std::env::var(var).ok())
+        };
+        self.cmd = match &self.cmd {
+            suite::Cmd::Shell(cmd) => suite::Cmd::Shell(
+                interpolate_with(cmd, lookup).map_err(in_suite)?,
+            ),
+            suite::Cmd::Argv(argv) => suite::Cmd::Argv(
+                argv.iter()
+                    .map(|arg| interpolate_with(arg, lookup))
+                    .collect::<Result<_, _>>()
+                    .map_err(in_suite)?,
+            ),
+        };
+        self.paths = self
+            .paths
+            .iter()
+            .map(|pattern| interpolate(pattern))
+            .collect::<Result<_, _>>()
+            .map_err(in_suite)?;
+        self.expect_dir = self
+            .expect_dir
+            .as_deref()
+            .map(interpolate_path)
+            .transpose()
+            .map_err(in_suite)?;
+        Ok(self)
+    }
+
+    /// Fill in the options that are not specified by this suite using
+    /// `defaults`. The default `expect_dir` is not used by suites that
+    /// specify an `expect_path`.
+    fn inherit(mut self, defaults: &Defaults) -> Self {
+        self.timeout = self.timeout.or(defaults.timeout);
+        self.grace_period = self.grace_period.or(defaults.grace_period);
+        if self.expect_path.is_none() {
+            self.expect_dir =
+                self.expect_dir.or_else(|| defaults.expect_dir.clone());
+        }
+        self.env = defaults.inherit_env(self.env.take());
+        self.shell = self.shell.or_else(|| defaults.shell.clone());
+        self.limits = defaults.inherit_limits(self.limits.take());
+        self
+    }
+
+    /// Expand a suite with a `matrix` into one suite for each combination of
+    /// the matrix values. The name of each suite is suffixed with its values,
+    /// for example, `Compile[backend=verilog,opt=-O2]`.
+    /// The values are substituted verbatim. Keys that are the names of
+    /// placeholders, keys without values, and empty matrices are errors
+    /// because they would silently remove the suite.
+    fn expand(mut self) -> Result<Vec<Self>, errors::RuntError> {
+        let matrix = match self.matrix.take() {
+            Some(matrix) => matrix,
+            None => return Ok(vec![self]),
+        };
+        if let Some(key) = matrix
+            .keys()
+            .find(|key| suite::PLACEHOLDERS.contains(&key.as_str()))
+        {
+            return Err(errors::RuntError(format!(
+                "{}: Matrix key `{}` is the name of a placeholder.",
+                self.name, key
+            )));
+        }
+        if matrix.is_empty() {
+            return Err(errors::RuntError(format!(
+                "{}: Matrix does not have any keys.",
+                self.name
+            )));
+        }
+        if let Some((key, _)) =
+            matrix.iter().find(|(_, values)| values.is_empty())
+        {
+            return Err(errors::RuntError(format!(
+                "{}: Matrix key `{}` does not have any values.",
+                self.name, key
+            )));
+        }
+
+        // Compute all combinations of the matrix values.
+        let combinations = matrix.iter().fold(
+            vec![vec![]],
+            |combs: Vec<Vec<(&String, &String)>>, (key, values)| {
+                combs
+                    .into_iter()
+                    .flat_map(|comb| {
+                        values.iter().map(move |value| {
+                            let mut comb = comb.clone();
+                            comb.push((key, value));
+                            comb
+                        })
+                    })
+                    .collect()
+            },
+        );
+
+        Ok(combinations
+            .into_iter()
+            .map(|comb| {
+                let subst = |template: &str| {
+                    comb.iter().fold(template.to_string(), |acc, (k, v)| {
+                        acc.replace(&format!("{{{}}}", k), v)
+                    })
+                };
+                let mut conf = self.clone();
+                conf.name = format!(
+                    "{}[{}]",
+                    self.name,
+                    comb.iter()
+                        .map(|(k, v)| format!("{}={}", k, v))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                );
+                conf.cmd = match &self.cmd {
+                    suite::Cmd::Shell(cmd) => suite::Cmd::Shell(subst(cmd)),
+                    suite::Cmd::Argv(argv) => suite::Cmd::Argv(
+                        argv.iter().map(|arg| subst(arg)).collect(),
+                    ),
+                };
+                conf.expect_dir = self
+                    .expect_dir
+                    .as_ref()
+                    .map(|dir| subst(&dir.to_string_lossy()).into());
+                conf.expect_path = self.expect_path.as_deref().map(subst);
+                conf
+            })
+            .collect())
+    }
+
+    /// Rebase a suite defined in the configuration under the directory `dir`
+    /// so that its paths are relative to the including configuration.
+    /// See [Relative::rebase].
+    fn rebase(mut self, dir: &Path) -> Self {
+        Relative {
+            name: &mut self.name,
+            paths: &mut self.paths,
+            expect_dir: &mut self.expect_dir,
+            depends_on: &mut self.depends_on,
+            cwd: &mut self.cwd,
+            dir: &mut self.dir,
+        }
+        .rebase(dir);
+        for patterns in self.exclude_paths.iter_mut().chain(&mut self.xfail) {
+            rebase_patterns(dir, patterns);
+        }
+        self
+    }
+}
+
+/// Message for an unknown key error that points at the key itself. The parser
+/// reports unknown keys at a position near their table, so the key is found by
+/// scanning `contents` for the last assignment to it at or before that
+/// position. Returns `None` if `err` is not an unknown key error or the key is
+/// not found.
+fn unknown_key_error(contents: &str, err: &toml::de::Error) -> Option<String> {
+    let msg = err.to_string();
+    let key = msg.strip_prefix("unknown field `")?;
+    let key = &key[..key.find('`')?];
+    let assign = regex::Regex::new(&format!(
+        r#"(?:^|[{{,])\s*("{0}"|{0})\s*="#,
+        regex::escape(key)
+    ))
+    .ok()?;
+    let found: Vec<(usize, usize)> = contents
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            Some((line, assign.captures(text)?.get(1)?.start()))
+        })
+        .collect();
+    let reported = err.line_col().map_or(0, |(line, _)| line);
+    let (line, col) = found
+        .iter()
+        .rev()
+        .find(|(line, _)| *line <= reported)
+        .or_else(|| found.first())?;
+    let end = msg
+        .find(" for key `")
+        .or_else(|| msg.find(" at line "))
+        .unwrap_or(msg.len());
+    Some(format!(
+        "{} at line {} column {}",
+        &msg[..end],
+        line + 1,
+        col + 1
+    ))
 }
 
 impl Config {
+    /// Check if the current `runt` is compatible with the version specified in
+    /// the configuration at `conf_path`.
+    /// The version can either be a semver requirement like `"0.4"` or
+    /// `">=0.4, <0.6"`, or an exact version. An exact version that is older
+    /// than the tool version but has the same major version generates a
+    /// warning, including for `0.x` versions. Newer versions are errors.
+    fn check_version(&self, conf_path: &Path) -> Result<(), errors::RuntError> {
+        let tool = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+        let mismatch = || {
+            errors::RuntError(format!("Runt version mismatch. {} requires: {}, tool version: {}.\nRun `cargo install runt` to get the latest version of runt.", conf_path.to_string_lossy(), self.ver, tool))
+        };
+
+        if let Ok(ver) = Version::parse(&self.ver) {
+            if ver == tool {
+                return Ok(());
+            }
+            if ver.major != tool.major || ver > tool {
+                return Err(mismatch());
+            }
+            errors::warn(format!(
+                "{} requires runt {} but the tool version is {}. Use a requirement like \"{}.{}\" to accept compatible versions.",
+                conf_path.to_string_lossy(),
+                ver,
+                tool,
+                tool.major,
+                tool.minor
+            ));
+            return Ok(());
+        }
+
+        let req = VersionReq::parse(&self.ver).map_err(|err| {
+            errors::RuntError(format!(
+                "{}: Invalid version requirement `{}`: {}",
+                conf_path.to_string_lossy(),
+                self.ver,
+                err
+            ))
+        })?;
+        if req.matches(&tool) {
+            Ok(())
+        } else {
+            Err(mismatch())
+        }
+    }
+
     /// Create a configuration by reading a `runt.toml` file.
-    /// Ensures that the version number specified in the `runt.toml` matches
-    /// the version of the installed `runt` binary.
+    /// Ensures that the version specified in the `runt.toml` is compatible
+    /// with the version of the installed `runt` binary.
+    /// Configurations listed under `include` are loaded recursively and their
+    /// test suites are added to this configuration.
     pub fn from_path(conf_dir: &Path) -> Result<Self, errors::RuntError> {
         // Error if runt.toml doesn't exist.
         let conf_path = conf_dir.join("runt.toml");
-        let contents = &std::fs::read_to_string(&conf_path).map_err(|_| {
+        if !conf_path.exists() {
+            return Err(errors::RuntError(format!(
+                "{} is missing. Runt expects a directory with a runt.toml file.",
+                conf_path.to_string_lossy()
+            )));
+        }
+        Self::from_file(&conf_path, &mut vec![], &mut HashSet::new())
+    }
+
+    /// Read the configuration file at `conf_path` and its includes.
+    /// `parents` tracks the configurations currently being loaded to detect
+    /// cyclic includes and `loaded` the configurations that have been loaded
+    /// so that configurations matched by several include patterns are only
+    /// loaded once.
+    fn from_file(
+        conf_path: &Path,
+        parents: &mut Vec<PathBuf>,
+        loaded: &mut HashSet<PathBuf>,
+    ) -> Result<Self, errors::RuntError> {
+        let contents = &std::fs::read_to_string(conf_path).map_err(|err| {
             errors::RuntError(format!(
-            "{} is missing. Runt expects a directory with a runt.toml file.",
-            conf_path.to_str().unwrap()
-        ))
+                "Failed to read {}: {}",
+                conf_path.to_string_lossy(),
+                err
+            ))
         })?;
 
-        let conf: Config = toml::from_str(contents).map_err(|err| {
+        let mut conf: Config = toml::from_str(contents).map_err(|err| {
             errors::RuntError(format!(
                 "Failed to parse {}: {}",
-                conf_path.to_str().unwrap(),
-                err
+                conf_path.to_string_lossy(),
+                unknown_key_error(contents, &err)
+                    .unwrap_or_else(|| err.to_string())
             ))
         })?;
 
-        // Check if the current `runt` matches the version specified in
-        // the configuration.
-        if env!("CARGO_PKG_VERSION") != conf.ver {
-            return Err(errors::RuntError(format!("Runt version mismatch. Configuration requires: {}, tool version: {}.\nRun `cargo install runt` to get the latest version of runt.", conf.ver, env!("CARGO_PKG_VERSION"))));
+        conf.check_version(conf_path)?;
+        let in_conf = |err: errors::RuntError| {
+            errors::RuntError(format!(
+                "{}: {}",
+                conf_path.to_string_lossy(),
+                err
+            ))
+        };
+        conf.defaults = conf.defaults.interpolate().map_err(in_conf)?;
+        let defaults = &conf.defaults;
+        conf.tests = conf
+            .tests
+            .into_iter()
+            .map(|suite| {
+                suite.interpolate(defaults)?.inherit(defaults).expand()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(in_conf)?
+            .into_iter()
+            .flatten()
+            .collect();
+        conf.directives = conf
+            .directives
+            .into_iter()
+            .map(|suite| Ok(suite.interpolate()?.inherit(defaults)))
+            .collect::<Result<_, _>>()
+            .map_err(in_conf)?;
+
+        let canonical = conf_path.canonicalize()?;
+        if parents.contains(&canonical) {
+            return Err(errors::RuntError(format!(
+                "{} includes itself.",
+                conf_path.to_string_lossy()
+            )));
+        }
+        loaded.insert(canonical.clone());
+        parents.push(canonical);
+
+        // Drop `.` components so that glob results can be stripped of the
+        // configuration directory.
+        let conf_dir = conf_path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .components()
+            .filter(|comp| comp != &Component::CurDir)
+            .collect::<PathBuf>();
+        for pattern in conf.include.iter().flatten() {
+            let paths = glob::glob(&conf_dir.join(pattern).to_string_lossy())
+                .map_err(|err| {
+                    errors::RuntError(format!(
+                        "{}: Invalid include pattern `{}`: {}",
+                        conf_path.to_string_lossy(),
+                        pattern,
+                        err
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| errors::RuntError(err.to_string()))?;
+
+            if paths.is_empty() {
+                return Err(errors::RuntError(format!(
+                    "{}: Include pattern `{}` did not match any configuration.",
+                    conf_path.to_string_lossy(),
+                    pattern
+                )));
+            }
+
+            for path in paths {
+                // Directories are expected to contain a runt.toml.
+                let path = if path.is_dir() {
+                    path.join("runt.toml")
+                } else {
+                    path
+                };
+                // Skip the configuration itself, which is matched by
+                // patterns like `**/runt.toml`, and configurations that were
+                // already loaded through another pattern or include.
+                if let Ok(canonical) = path.canonicalize() {
+                    if Some(&canonical) == parents.last()
+                        || (loaded.contains(&canonical)
+                            && !parents.contains(&canonical))
+                    {
+                        continue;
+                    }
+                }
+                let sub = Self::from_file(&path, parents, loaded)?;
+                let sub_dir = path
+                    .parent()
+                    .and_then(|dir| dir.strip_prefix(&conf_dir).ok())
+                    .unwrap_or_else(|| Path::new(""))
+                    .to_path_buf();
+                conf.tests.extend(
+                    sub.tests.into_iter().map(|suite| suite.rebase(&sub_dir)),
+                );
+                conf.directives.extend(
+                    sub.directives
+                        .into_iter()
+                        .map(|suite| suite.rebase(&sub_dir)),
+                );
+            }
         }
+        parents.pop();
 
         Ok(conf)
     }
 }
 
-impl From<SuiteConfig> for suite::Suite {
+impl Config {
+    /// Dependencies that do not name a suite in this configuration. Returns
+    /// pairs of the name of the dependent suite and the unknown name.
+    pub fn unknown_dependencies(&self) -> Vec<(&str, &str)> {
+        let names: Vec<&str> = self
+            .tests
+            .iter()
+            .map(|conf| conf.name.as_str())
+            .chain(self.directives.iter().map(|conf| conf.name.as_str()))
+            .collect();
+        let deps = self
+            .tests
+            .iter()
+            .map(|conf| (&conf.name, &conf.depends_on))
+            .chain(
+                self.directives
+                    .iter()
+                    .map(|conf| (&conf.name, &conf.depends_on)),
+            );
+        deps.flat_map(|(name, deps)| {
+            deps.iter()
+                .flatten()
+                .map(move |dep| (name.as_str(), dep.as_str()))
+        })
+        .filter(|(_, dep)| {
+            !names.iter().any(|name| suite::name_matches(name, dep))
+        })
+        .collect()
+    }
+}
+
+impl Picker for Config {
+    /// Gather the test suites defined under `tests` and `directives`.
+    fn suites(&self) -> Result<Vec<suite::Suite>, errors::RuntError> {
+        if let Some((name, dep)) = self.unknown_dependencies().first() {
+            return Err(errors::RuntError(format!(
+                "{}: depends_on names unknown suite `{}`.",
+                name, dep
+            )));
+        }
+        let mut suites = self
+            .tests
+            .iter()
+            .cloned()
+            .map(suite::Suite::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        for conf in &self.directives {
+            suites.extend(conf.suites()?);
+        }
+        Ok(suites)
+    }
+}
+
+impl TryFrom<SuiteConfig> for suite::Suite {
+    type Error = errors::RuntError;
+
     /// Transform a list of glob patterns into matching paths and
-    /// list of errors.
-    fn from(conf: SuiteConfig) -> Self {
-        // Arcane sorcery
-        let all_paths = conf
-            .paths
-            .into_iter()
-            .map(|pattern| glob::glob(&pattern))
-            .collect::<Result<Vec<_>, glob::PatternError>>()
-            .expect("Glob pattern error")
-            .into_iter()
-            .flat_map(|paths| paths.collect::<Vec<_>>())
-            .collect::<Result<Vec<_>, _>>()
-            .expect("Failed to read globbed path");
+    /// remove the paths matching the `exclude_paths` patterns.
+    /// Warns about patterns that do not match any files or errors if
+    /// `deny_empty_paths` is set.
+    fn try_from(conf: SuiteConfig) -> Result<Self, Self::Error> {
+        if conf.expect_dir.is_some() && conf.expect_path.is_some() {
+            return Err(errors::RuntError(format!(
+                "{}: expect_dir and expect_path cannot be used together.",
+                conf.name
+            )));
+        }
+
+        if matches!(&conf.cmd, suite::Cmd::Argv(argv) if argv.is_empty()) {
+            return Err(errors::RuntError(format!(
+                "{}: cmd cannot be empty.",
+                conf.name
+            )));
+        }
+
+        let limits = conf
+            .limits
+            .as_ref()
+            .map(|limits| limits.parse(&conf.name))
+            .transpose()?
+            .unwrap_or_default();
+
+        let shell = ShellConfig::resolve(conf.shell, &conf.name)?;
 
-        suite::Suite {
+        let all_paths = super::gather_paths(
+            &conf.name,
+            &conf.paths,
+            conf.exclude_paths.as_deref().unwrap_or_default(),
+            conf.deny_empty_paths,
+        )?;
+
+        let xfail = super::compile_patterns(
+            &conf.name,
+            conf.xfail.as_deref().unwrap_or_default(),
+        )?;
+        let xfail = all_paths
+            .iter()
+            .filter(|path| xfail.iter().any(|pat| pat.matches_path(path)))
+            .cloned()
+            .collect();
+
+        Ok(suite::Suite {
             paths: all_paths,
             config: suite::Config {
                 name: conf.name,
                 cmd: conf.cmd,
+                shell,
                 expect_dir: conf.expect_dir,
+                expect_path: conf.expect_path,
+                dir: conf.dir,
                 timeout: Duration::from_secs(conf.timeout.unwrap_or(1200)),
+                env: conf.env.unwrap_or_default(),
+                cwd: super::parse_cwd(conf.cwd.as_deref()),
+                setup: conf.setup,
+                teardown: conf.teardown,
+                skip: false,
+                stdin: match conf.stdin {
+                    None => suite::Stdin::Null,
+                    Some(stdin) if stdin == "null" => suite::Stdin::Null,
+                    Some(stdin) if stdin == "file" => suite::Stdin::File,
+                    Some(text) => suite::Stdin::Text(text),
+                },
+                tags: conf.tags.unwrap_or_default(),
+                depends_on: conf.depends_on.unwrap_or_default(),
+                retries: conf.retries.unwrap_or(0),
+                xfail,
+                grace_period: Duration::from_secs(
+                    conf.grace_period.unwrap_or(5),
+                ),
+                limits,
             },
-        }
+        })
     }
 }
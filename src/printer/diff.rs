@@ -47,7 +47,7 @@ impl PrintInfo<'_> {
 
 /// Given a changeset, generate a vector with a diff representation that tracks
 /// line numbers.
-fn diff_with_lineno(changes: &Changeset) -> Vec<PrintInfo> {
+fn diff_with_lineno(changes: &Changeset) -> Vec<PrintInfo<'_>> {
     // Track line number for original and new strings.
     let mut line_a = 0;
     let mut line_b = 0;